@@ -93,6 +93,7 @@ pub mod {ext}_{stem}
     vulkano_shaders::shader! {{
         ty: \"{shader_type}\",
         bytes: \"{out_folder}/{file_name}\",
+        custom_derives: [Clone, Copy, PartialEq],
     }}
 }}
 "
@@ -5,14 +5,16 @@ layout(location = 1) in vec3 color;
 
 layout(location = 0) out vec3 out_color;
 
-layout(binding = 0) uniform Ubo {
+layout( push_constant ) uniform Pc {
     mat4 model;
-    mat4 view;
-    mat4 proj;
+};
+
+layout( set = 0, binding = 0 ) uniform Camera {
+    mat4 view_projection;
 };
 
 void main()
 {
-    gl_Position =  proj * view * model * vec4(pos, 1.0f);
+    gl_Position =  view_projection * model * vec4(pos, 1.0f);
     out_color = color;
 }
\ No newline at end of file
@@ -0,0 +1,20 @@
+#version 450
+
+layout(location = 0) in vec2 pos;
+
+// per instance
+layout(location = 1) in vec2 offset;
+layout(location = 2) in float scale;
+layout(location = 3) in vec3 color;
+
+layout(location = 0) out vec3 out_color;
+
+layout( set = 0, binding = 0 ) uniform CartesianToNorm {
+    mat4 projection;
+};
+
+void main()
+{
+    gl_Position = projection * vec4(pos * scale + offset, 0.0, 1.0);
+    out_color = color;
+}
\ No newline at end of file
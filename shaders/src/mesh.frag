@@ -0,0 +1,23 @@
+#version 450
+
+layout(location = 0) in vec3 normal;
+layout(location = 1) in vec2 uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout( push_constant ) uniform Pc {
+    mat4 model;
+    vec4 base_color;
+};
+
+layout(set = 1, binding = 0) uniform sampler2D base_color_texture;
+
+const vec3 LIGHT_DIRECTION = normalize(vec3(0.3f, 1.0f, 0.5f));
+const float AMBIENT = 0.3f;
+
+void main()
+{
+    vec4 color = base_color * texture(base_color_texture, uv);
+    float diffuse = max(dot(normalize(normal), LIGHT_DIRECTION), 0.0f);
+    out_color = vec4(color.rgb * (AMBIENT + (1.0f - AMBIENT) * diffuse), color.a);
+}
\ No newline at end of file
@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec2 uv;
+
+layout(location = 0) out vec3 out_normal;
+layout(location = 1) out vec2 out_uv;
+
+layout( push_constant ) uniform Pc {
+    mat4 model;
+    vec4 base_color;
+};
+
+layout( set = 0, binding = 0 ) uniform Camera {
+    mat4 view_projection;
+};
+
+void main()
+{
+    gl_Position =  view_projection * model * vec4(position, 1.0f);
+    out_normal = mat3(model) * normal;
+    out_uv = uv;
+}
\ No newline at end of file
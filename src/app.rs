@@ -1,43 +1,126 @@
+use crate::camera_controller::{CameraController, FlyController, OrbitController};
+use crate::graphics::error::GraphicsError;
+use crate::graphics::scene::{NodeId, SceneGraph, Transform};
 use crate::graphics::Graphics;
 use crate::input::ButtonState;
 use crate::input::Input;
 use std::sync::Arc;
+use std::time::Instant;
 
 static mut TEST: u32 = 0;
 
-mod drawables {
+// F12
+const SCREENSHOT_KEY: u32 = 88;
+// C, switches between the orbit and the free-fly camera
+const CAMERA_MODE_KEY: u32 = 46;
+// E, saves the block drawn in the editor
+const EXPORT_BLOCK_KEY: u32 = 18;
+const BLOCK_PATH: &str = "block.ron";
+
+pub mod drawables {
+    mod cube;
     mod grid;
+    mod instanced_squares;
+    mod model;
+    mod pixel_block_editor;
     mod square;
+    mod textest;
+    pub mod triangle;
 
+    pub use cube::Cube;
     pub use grid::Grid;
+    pub use instanced_squares::{InstancedSquares, SquareInstance};
+    pub use model::Model;
+    pub use pixel_block_editor::PixelBlockEditor;
     pub use square::Square;
+    pub use textest::TexturedSquare;
 }
 
 pub struct App {
     input: Arc<Input>,
+    scene: Arc<SceneGraph>,
     grid: drawables::Grid,
     square: drawables::Square,
+    square_node: NodeId,
+    cube: drawables::Cube,
+    block_editor: drawables::PixelBlockEditor,
+    camera_controller: Box<dyn CameraController>,
+    flying: bool,
+    last_frame: Instant,
 }
 
 impl App {
-    pub fn new(gfx: &mut Graphics, input: Arc<Input>) -> Self {
-        Self {
+    pub fn new(gfx: &mut Graphics, input: Arc<Input>) -> Result<Self, GraphicsError> {
+        let scene = SceneGraph::new(gfx);
+        let grid = drawables::Grid::new(gfx, cgmath::Vector2 { x: 5, y: 4 }, 50.0)?;
+        let square = drawables::Square::new(gfx, cgmath::Vector2::new(0.0, 0.0), 10.0)?;
+        let cube = drawables::Cube::new(gfx, true)?;
+        // left of the grid
+        let block_editor = drawables::PixelBlockEditor::new(
+            gfx,
+            cgmath::Vector2::new(4, 4),
+            20.0,
+            cgmath::Vector2::new(-220.0, 0.0),
+        )?;
+
+        // the square is parented to the grid so moving the grid moves both
+        let grid_node = scene.create_node(None, Transform::identity());
+        let square_node = scene.create_node(Some(grid_node), Transform::identity());
+        grid.attach_to(&scene, grid_node);
+        square.attach_to(&scene, square_node);
+
+        Ok(Self {
             input: input,
-            grid: drawables::Grid::new(gfx, cgmath::Vector2 { x: 5, y: 4 }, 50.0),
-            square: drawables::Square::new(gfx, cgmath::Vector2::new(0.0, 0.0), 10.0),
-        }
+            scene: scene,
+            grid: grid,
+            square: square,
+            square_node: square_node,
+            cube: cube,
+            block_editor: block_editor,
+            camera_controller: Box::new(OrbitController::from_camera(&gfx.get_utils().camera)),
+            flying: false,
+            last_frame: Instant::now(),
+        })
+    }
+
+    pub fn resize_callback(&self, gfx: &mut Graphics) -> Result<(), GraphicsError> {
+        gfx.recreate_swapchain()
     }
 
-    pub fn resize_callback(&self, gfx: &mut Graphics) {
-        gfx.recreate_swapchain();
+    pub fn screenshot_requested(&self) -> bool {
+        self.input.keyboard.is_key_pressed(SCREENSHOT_KEY)
     }
 
-    pub fn run(&self, _gfx: &Graphics) {
-        self.square.transform.access_data(|data| {
-            data.transform = match self.input.keyboard.get_key_state(28) {
-                Some(ButtonState::Held(_)) => cgmath::Matrix4::from_scale(10.0).into(),
-                _ => cgmath::Matrix4::from_scale(5.0).into(),
+    pub fn run(&mut self, gfx: &Graphics) {
+        let now = Instant::now();
+        let delta = now - self.last_frame;
+        self.last_frame = now;
+
+        let camera = &gfx.get_utils().camera;
+        if self.input.keyboard.is_key_pressed(CAMERA_MODE_KEY) {
+            self.flying = !self.flying;
+            self.camera_controller = if self.flying {
+                Box::new(FlyController::from_camera(camera))
+            } else {
+                Box::new(OrbitController::from_camera(camera))
+            };
+        }
+        self.camera_controller.update(&self.input, camera, delta);
+
+        self.block_editor.update(&self.input);
+        if self.input.keyboard.is_key_pressed(EXPORT_BLOCK_KEY) {
+            match self.block_editor.get_shape().save(BLOCK_PATH) {
+                Ok(()) => log::info!("Saved block to {BLOCK_PATH}"),
+                Err(e) => log::error!("Failed to save block: {e}"),
             }
+        }
+
+        let scale = match self.input.keyboard.get_key_state(28) {
+            Some(ButtonState::Held(_)) => 1.0,
+            _ => 0.5,
+        };
+        self.scene.update_transform(self.square_node, |transform| {
+            transform.scale = cgmath::Vector3::new(scale, scale, scale);
         });
 
         //// Input test
@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Point3, Rad, Vector3};
+
+use crate::graphics::camera::Camera;
+use crate::input::mouse::{MOUSE_MIDDLE, MOUSE_RIGHT};
+use crate::input::Input;
+
+// scancodes, like the rest of the input handling
+const KEY_W: u32 = 17;
+const KEY_A: u32 = 30;
+const KEY_S: u32 = 31;
+const KEY_D: u32 = 32;
+const KEY_SPACE: u32 = 57;
+const KEY_LEFT_CTRL: u32 = 29;
+const KEY_LEFT_SHIFT: u32 = 42;
+
+// Cameras use a downwards up vector to make up for vulkan's clip space, which mirrors world x on
+// screen. World +y still points up on screen, but +x points left.
+
+/// Keeps the pitch away from the poles where the view direction lines up with the up vector.
+const MAX_PITCH: Rad<f32> = Rad(1.55);
+
+/// Moves a camera based on user input, called once per frame.
+pub trait CameraController {
+    fn update(&mut self, input: &Input, camera: &Camera, delta: Duration);
+}
+
+/// Rotates around a target while the middle mouse button is dragged, the wheel zooms in and out.
+/// The left button is left to the block editor.
+pub struct OrbitController {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+    /// Radians per unit of mouse movement.
+    pub rotate_speed: f32,
+    /// Fraction of the distance per scrolled line.
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+}
+
+impl OrbitController {
+    /// Starts out wherever the camera currently is.
+    pub fn from_camera(camera: &Camera) -> Self {
+        let settings = camera.get_settings();
+        let offset = settings.position - settings.target;
+        let distance = offset.magnitude().max(f32::EPSILON);
+
+        Self {
+            target: settings.target,
+            distance: distance,
+            yaw: Rad(offset.x.atan2(offset.z)),
+            pitch: Rad((offset.y / distance).clamp(-1.0, 1.0).asin()),
+            rotate_speed: 0.005,
+            zoom_speed: 0.1,
+            min_distance: 0.1,
+            max_distance: 100.0,
+        }
+    }
+
+    fn position(&self) -> Point3<f32> {
+        let (yaw_sin, yaw_cos) = self.yaw.0.sin_cos();
+        let (pitch_sin, pitch_cos) = self.pitch.0.sin_cos();
+        self.target
+            + Vector3::new(pitch_cos * yaw_sin, pitch_sin, pitch_cos * yaw_cos) * self.distance
+    }
+}
+
+impl CameraController for OrbitController {
+    fn update(&mut self, input: &Input, camera: &Camera, _delta: Duration) {
+        if input.mouse.is_button_down(MOUSE_MIDDLE) {
+            let movement = input.mouse.mouse_movement.get();
+            self.yaw += Rad(movement.x as f32 * self.rotate_speed);
+            self.pitch += Rad(movement.y as f32 * self.rotate_speed);
+            self.pitch = Rad(self.pitch.0.clamp(-MAX_PITCH.0, MAX_PITCH.0));
+        }
+
+        let scroll = input.mouse.scroll_delta.get() as f32;
+        self.distance = (self.distance * (1.0 - scroll * self.zoom_speed))
+            .clamp(self.min_distance, self.max_distance);
+
+        camera.look_at(self.position(), self.target);
+    }
+}
+
+/// WASD to move, space and ctrl to go up and down, shift to go faster.
+/// Looks around while the right mouse button is held.
+pub struct FlyController {
+    pub position: Point3<f32>,
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+    /// Units per second.
+    pub speed: f32,
+    pub fast_multiplier: f32,
+    /// Radians per unit of mouse movement.
+    pub look_speed: f32,
+}
+
+impl FlyController {
+    /// Starts out wherever the camera currently is, looking at its target.
+    pub fn from_camera(camera: &Camera) -> Self {
+        let settings = camera.get_settings();
+        let forward = settings.target - settings.position;
+        let forward = if forward.magnitude() > f32::EPSILON {
+            forward.normalize()
+        } else {
+            Vector3::new(0.0, 0.0, -1.0)
+        };
+
+        Self {
+            position: settings.position,
+            yaw: Rad(forward.x.atan2(-forward.z)),
+            pitch: Rad(forward.y.clamp(-1.0, 1.0).asin()),
+            speed: 2.0,
+            fast_multiplier: 4.0,
+            look_speed: 0.003,
+        }
+    }
+
+    /// Yaw 0 and pitch 0 look down -z.
+    fn forward(&self) -> Vector3<f32> {
+        let (yaw_sin, yaw_cos) = self.yaw.0.sin_cos();
+        let (pitch_sin, pitch_cos) = self.pitch.0.sin_cos();
+        Vector3::new(pitch_cos * yaw_sin, pitch_sin, -pitch_cos * yaw_cos)
+    }
+}
+
+impl CameraController for FlyController {
+    fn update(&mut self, input: &Input, camera: &Camera, delta: Duration) {
+        if input.mouse.is_button_down(MOUSE_RIGHT) {
+            let movement = input.mouse.mouse_movement.get();
+            self.yaw -= Rad(movement.x as f32 * self.look_speed);
+            self.pitch -= Rad(movement.y as f32 * self.look_speed);
+            self.pitch = Rad(self.pitch.0.clamp(-MAX_PITCH.0, MAX_PITCH.0));
+        }
+
+        let world_up = Vector3::new(0.0, 1.0, 0.0);
+        let forward = self.forward();
+        // right on screen
+        let right = world_up.cross(forward).normalize();
+
+        let keyboard = &input.keyboard;
+        let mut direction = Vector3::new(0.0, 0.0, 0.0);
+        let axes = [
+            (KEY_W, forward),
+            (KEY_S, -forward),
+            (KEY_D, right),
+            (KEY_A, -right),
+            (KEY_SPACE, world_up),
+            (KEY_LEFT_CTRL, -world_up),
+        ];
+        for (key, axis) in axes {
+            if keyboard.is_key_down(key) {
+                direction += axis;
+            }
+        }
+
+        if direction.magnitude2() > 0.0 {
+            let speed = if keyboard.is_key_down(KEY_LEFT_SHIFT) {
+                self.speed * self.fast_multiplier
+            } else {
+                self.speed
+            };
+            self.position += direction.normalize() * speed * delta.as_secs_f32();
+        }
+
+        camera.look_at(self.position, self.position + forward);
+    }
+}
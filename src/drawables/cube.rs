@@ -1,38 +1,32 @@
 use std::sync::Arc;
 
-use rand::distributions::uniform;
 use vulkano::{buffer::BufferContents, pipeline::graphics::vertex_input::Vertex, shader::ShaderStages};
-use crate::graphics::{drawable::{DrawableEntry, GenericDrawable}, Graphics, bindable::{self, UniformBuffer}, shaders::{vert_3dColored, frag_3dColored}};
-use cgmath::{prelude::*, Matrix4, Perspective, PerspectiveFov, Point3, Vector3, Rad, Deg};
+use crate::graphics::{drawable::{DrawableEntry, GenericDrawable, SharedKey}, error::GraphicsError, Graphics, scene::{NodeId, SceneGraph}, bindable::{self, PushConstant}, bounds::PositionedVertex, registry::DrawableRegistry, shaders::{vert_3dColored, frag_3dColored}};
+use cgmath::prelude::*;
 
-pub use vert_3dColored::Ubo;
+pub use vert_3dColored::Pc;
 
 pub struct Cube
 {
     pub entry: DrawableEntry,
-    pub uniform: Arc<UniformBuffer<Ubo>>
+    /// Writing the model here directly moves the cube without culling knowing, use
+    /// `set_transform` or `attach_to` instead.
+    pub pc: Arc<PushConstant<Pc>>,
+    registry: DrawableRegistry,
 }
 
 impl Cube
 {
-    pub fn new(gfx: &mut Graphics, create_registered: bool) -> Cube
+    pub fn new(gfx: &mut Graphics, create_registered: bool) -> Result<Cube, GraphicsError>
     {
-        let window_extent = gfx.get_window().inner_size();
-        let aspect = window_extent.width as f32 / window_extent.height as f32;
-        let uniform = UniformBuffer::new(gfx, 0, Ubo {
+        let pc = PushConstant::new(gfx, 0, Pc {
             model: cgmath::Matrix4::identity().into(),
-            view: cgmath::Matrix4::look_at_rh(
-                Point3{x: 0.0, y: 1.0, z: 1.3},
-                Point3{x: 0.0, y: 0.0, z: 0.0},
-                Vector3{x: 0.0, y: -1.0, z: 0.0}
-            ).into(),
-            proj: cgmath::perspective(Deg(90.0), aspect, 0.2, 10.0).into()
         }, ShaderStages::VERTEX);
 
-        let mut entry = GenericDrawable::new(gfx, 1, || {
-            vec![
-                uniform.clone()
-            ]
+        let mut entry = GenericDrawable::new(gfx, SharedKey::of::<Self>(), || {
+            Ok(vec![
+                pc.clone()
+            ])
         }, || {
             #[derive(BufferContents, Vertex)]
             #[repr(C)]
@@ -42,6 +36,11 @@ impl Cube
                 #[format(R32G32B32_SFLOAT)]
                 pub color: [f32; 3],
             }
+            impl PositionedVertex for Vertex {
+                fn position(&self) -> [f32; 3] {
+                    self.pos
+                }
+            }
             let vertices: Vec<Vertex> = vec![
                 Vertex{pos: [-0.5, -0.5, -0.5], color: [1.0, 1.0, 0.0]},
                 Vertex{pos: [-0.5,  0.5, -0.5], color: [0.0, 1.0, 1.0]},
@@ -74,21 +73,41 @@ impl Cube
                 0, 2, 4,    2, 6, 4,
             ];
             
-            vec![
-                bindable::VertexShader::from_module(vert_3dColored::load(gfx.get_device()).unwrap()),
-                bindable::FragmentShader::from_module(frag_3dColored::load(gfx.get_device()).unwrap()),
-                bindable::IndexBuffer::new(&gfx, indices),
-                bindable::VertexBuffer::new(&gfx, vertices),
-            ]
-        });
+            Ok(vec![
+                bindable::VertexShader::from_module(vert_3dColored::load(gfx.get_device()).map_err(GraphicsError::vulkan)?),
+                bindable::FragmentShader::from_module(frag_3dColored::load(gfx.get_device()).map_err(GraphicsError::vulkan)?),
+                bindable::IndexBuffer::new(&gfx, indices)?,
+                bindable::VertexBuffer::new_bounded(&gfx, vertices)?,
+                gfx.get_utils().camera.get_uniform(),
+            ])
+        })?;
 
         if create_registered {
             gfx.register_drawable(&mut entry);
         }
 
-        Self {
+        Ok(Self {
             entry: entry,
-            uniform: uniform,
+            pc: pc,
+            registry: gfx.get_registry().clone(),
+        })
+    }
+
+    pub fn set_transform(&self, transform: cgmath::Matrix4<f32>)
+    {
+        self.pc.access_data(|data| data.model = transform.into());
+        if let Some(handle) = self.entry.get_handle() {
+            self.registry.set_transform(handle, transform);
+        }
+    }
+
+    /// The cube's model matrix follows `node` from the next frame on.
+    pub fn attach_to(&self, scene: &SceneGraph, node: NodeId)
+    {
+        let pc = self.pc.clone();
+        scene.attach(node, move |world| pc.access_data(|data| data.model = world.into()));
+        if let Some(handle) = self.entry.get_handle() {
+            scene.attach_drawable(node, handle);
         }
     }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use cgmath::Vector2;
+use cgmath::{Matrix4, Vector2};
 use vulkano::{
     buffer::BufferContents,
     pipeline::graphics::{
@@ -12,7 +12,9 @@ use vulkano::{
 
 use crate::graphics::{
     bindable::{self, PushConstant},
-    drawable::{DrawableEntry, GenericDrawable},
+    drawable::{DrawableEntry, GenericDrawable, RenderLayer, SharedKey},
+    error::GraphicsError,
+    scene::{NodeId, SceneGraph},
     shaders::{frag_solid_white, vert_cartesian_2d},
     Graphics,
 };
@@ -21,10 +23,15 @@ pub struct Grid {
     entry: DrawableEntry,
     pub pc: Arc<PushConstant<vert_cartesian_2d::Data>>,
     pub dimensions: Vector2<u32>,
+    cell_width: f32,
 }
 
 impl Grid {
-    pub fn new(gfx: &mut Graphics, dimensions: Vector2<u32>, cell_width: f32) -> Self {
+    pub fn new(
+        gfx: &mut Graphics,
+        dimensions: Vector2<u32>,
+        cell_width: f32,
+    ) -> Result<Self, GraphicsError> {
         let pc = bindable::PushConstant::new(
             gfx,
             0,
@@ -36,9 +43,10 @@ impl Grid {
 
         let mut entry = GenericDrawable::new(
             &gfx,
-            0,
+            // the vertices depend on the dimensions
+            SharedKey::with::<Self>((dimensions.x, dimensions.y)),
             || {
-                vec![pc.clone()] // no per instance bindables necessary
+                Ok(vec![pc.clone()]) // no per instance bindables necessary
             },
             || {
                 let mut vertices: Vec<Vertex> =
@@ -92,16 +100,16 @@ impl Grid {
                 // fill indices
                 indices.extend((4..vertices.len() as u32).into_iter());
 
-                vec![
+                Ok(vec![
                     bindable::VertexShader::from_module(
-                        vert_cartesian_2d::load(gfx.get_device()).unwrap(),
+                        vert_cartesian_2d::load(gfx.get_device()).map_err(GraphicsError::vulkan)?,
                     ),
                     bindable::FragmentShader::from_module(
-                        frag_solid_white::load(gfx.get_device()).unwrap(),
+                        frag_solid_white::load(gfx.get_device()).map_err(GraphicsError::vulkan)?,
                     ),
-                    bindable::IndexBuffer::new(&gfx, indices),
-                    bindable::VertexBuffer::new(&gfx, vertices),
-                    gfx.get_utils().cartesian_to_normalized.clone(),
+                    bindable::IndexBuffer::new(&gfx, indices)?,
+                    bindable::VertexBuffer::new(&gfx, vertices)?,
+                    gfx.get_utils().screen_camera.get_uniform(),
                     bindable::GodBindable::new(
                         |_, _| {},
                         |pipeline_builder, _| {
@@ -109,16 +117,32 @@ impl Grid {
                                 InputAssemblyState::new().topology(PrimitiveTopology::LineList);
                         },
                     ),
-                ]
+                ])
             },
-        );
+        )?;
 
-        gfx.register_drawable(&mut entry);
+        let handle = gfx.register_drawable(&mut entry);
+        gfx.get_registry().set_layer(handle, RenderLayer::Ui, 0);
 
-        Self {
+        Ok(Self {
             entry: entry,
             pc: pc,
             dimensions: dimensions,
+            cell_width: cell_width,
+        })
+    }
+
+    /// The grid follows `node` from the next frame on, scaled by its cell width.
+    pub fn attach_to(&self, scene: &SceneGraph, node: NodeId) {
+        let pc = self.pc.clone();
+        let cell_width = self.cell_width;
+        scene.attach(node, move |world| {
+            pc.access_data(|data| {
+                data.transform = (world * Matrix4::from_scale(cell_width)).into();
+            })
+        });
+        if let Some(handle) = self.entry.get_handle() {
+            scene.attach_drawable(node, handle);
         }
     }
 }
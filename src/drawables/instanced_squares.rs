@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use cgmath::Vector2;
+use vulkano::{buffer::BufferContents, pipeline::graphics::vertex_input::Vertex};
+
+use crate::graphics::{
+    bindable::{self, InstanceBuffer},
+    drawable::{DrawableEntry, GenericDrawable, SharedKey},
+    error::GraphicsError,
+    registry::DrawableHandle,
+    shaders::{frag_3dColored, vert_instanced_2d},
+    Graphics,
+};
+
+/// Field names have to match the inputs of `instanced_2d.vert`.
+#[derive(BufferContents, Vertex, Clone, Copy)]
+#[repr(C)]
+pub struct SquareInstance {
+    #[format(R32G32_SFLOAT)]
+    pub offset: [f32; 2],
+    #[format(R32_SFLOAT)]
+    pub scale: f32,
+    #[format(R32G32B32_SFLOAT)]
+    pub color: [f32; 3],
+}
+
+/// Any number of colored squares drawn with a single draw call.
+pub struct InstancedSquares {
+    entry: DrawableEntry,
+    pub instances: Arc<InstanceBuffer<SquareInstance>>,
+}
+
+impl InstancedSquares {
+    pub fn new(gfx: &mut Graphics, instances: Vec<SquareInstance>) -> Result<Self, GraphicsError> {
+        let instance_buffer = InstanceBuffer::new(gfx, instances)?;
+
+        let mut entry = GenericDrawable::new(
+            gfx,
+            SharedKey::of::<Self>(),
+            || Ok(vec![instance_buffer.clone()]),
+            || {
+                #[derive(BufferContents, Vertex)]
+                #[repr(C)]
+                struct Vertex {
+                    #[format(R32G32_SFLOAT)]
+                    pos: [f32; 2],
+                }
+
+                let vertices = vec![
+                    Vertex { pos: [-1.0, -1.0] },
+                    Vertex { pos: [1.0, -1.0] },
+                    Vertex { pos: [-1.0, 1.0] },
+                    Vertex { pos: [1.0, 1.0] },
+                ];
+
+                let indices: Vec<u32> = vec![0, 3, 1, 0, 2, 3];
+
+                Ok(vec![
+                    bindable::VertexBuffer::new(gfx, vertices)?,
+                    bindable::IndexBuffer::new(gfx, indices)?,
+                    bindable::VertexShader::from_module(
+                        vert_instanced_2d::load(gfx.get_device()).map_err(GraphicsError::vulkan)?,
+                    ),
+                    bindable::FragmentShader::from_module(
+                        frag_3dColored::load(gfx.get_device()).map_err(GraphicsError::vulkan)?,
+                    ),
+                    gfx.get_utils().screen_camera.get_uniform(),
+                ])
+            },
+        )?;
+
+        gfx.register_drawable(&mut entry);
+
+        Ok(Self {
+            entry: entry,
+            instances: instance_buffer,
+        })
+    }
+
+    pub fn get_handle(&self) -> Option<DrawableHandle> {
+        self.entry.get_handle()
+    }
+
+    /// Returns the index of the new square.
+    pub fn add_square(&self, pos: Vector2<f32>, radius: f32, color: [f32; 3]) -> usize {
+        self.instances.push(SquareInstance {
+            offset: pos.into(),
+            scale: radius,
+            color: color,
+        })
+    }
+
+    pub fn remove_square(&self, index: usize) {
+        self.instances.remove(index);
+    }
+}
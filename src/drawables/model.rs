@@ -0,0 +1,184 @@
+use std::{collections::HashMap, sync::Arc};
+
+use cgmath::Matrix4;
+use vulkano::shader::ShaderStages;
+
+use crate::graphics::{
+    bindable::{self, PushConstant, Texture},
+    drawable::{DrawableEntry, GenericDrawable, RenderLayer, SharedKey},
+    error::GraphicsError,
+    import::{self, MaterialData, ModelData},
+    registry::{DrawableHandle, DrawableRegistry},
+    scene::{NodeId, SceneGraph},
+    shaders::{frag_mesh, vert_mesh},
+    Graphics,
+};
+
+pub use vert_mesh::Pc;
+
+/// What placing the model needs of each part: its push constant, handle and local transform.
+type PartTransform = (Arc<PushConstant<Pc>>, Option<DrawableHandle>, Matrix4<f32>);
+
+struct ModelPart {
+    entry: DrawableEntry,
+    pc: Arc<PushConstant<Pc>>,
+    /// Relative to the model.
+    transform: Matrix4<f32>,
+}
+
+/// One registered drawable per primitive of every mesh node.
+pub struct Model {
+    parts: Vec<ModelPart>,
+    registry: DrawableRegistry,
+}
+
+impl Model {
+    /// Loads a `.gltf` or `.glb` file.
+    pub fn load_gltf(gfx: &mut Graphics, path: &str) -> Result<Self, GraphicsError> {
+        let data = import::gltf::load(path)?;
+        Self::from_data(gfx, path, &data)
+    }
+
+    /// Loads an `.obj` file along with its `.mtl` files and their png textures.
+    pub fn load_obj(gfx: &mut Graphics, path: &str) -> Result<Self, GraphicsError> {
+        let data = import::obj::load(path)?;
+        Self::from_data(gfx, path, &data)
+    }
+
+    /// Models created from the same `source` share their buffers, textures and pipelines,
+    /// so it should name where `data` came from.
+    pub fn from_data(
+        gfx: &mut Graphics,
+        source: &str,
+        data: &ModelData,
+    ) -> Result<Self, GraphicsError> {
+        let default_material = MaterialData::default();
+        let mut textures = HashMap::new();
+        let mut parts = Vec::new();
+
+        for node in &data.nodes {
+            for (primitive_index, primitive) in data.meshes[node.mesh].iter().enumerate() {
+                if primitive.mesh.indices.is_empty() {
+                    continue;
+                }
+
+                let material = primitive
+                    .material
+                    .and_then(|p| data.materials.get(p))
+                    .unwrap_or(&default_material);
+
+                let pc = PushConstant::new(
+                    gfx,
+                    0,
+                    Pc {
+                        model: node.transform.into(),
+                        base_color: material.base_color,
+                    },
+                    ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                );
+
+                let mut entry = GenericDrawable::new(
+                    gfx,
+                    SharedKey::with::<Self>((source, node.mesh, primitive_index)),
+                    || Ok(vec![pc.clone()]),
+                    || {
+                        Ok(vec![
+                            bindable::VertexShader::from_module(
+                                vert_mesh::load(gfx.get_device()).map_err(GraphicsError::vulkan)?,
+                            ),
+                            bindable::FragmentShader::from_module(
+                                frag_mesh::load(gfx.get_device()).map_err(GraphicsError::vulkan)?,
+                            ),
+                            primitive.mesh.vertex_buffer(gfx)?,
+                            primitive.mesh.index_buffer(gfx)?,
+                            // set 0
+                            gfx.get_utils().camera.get_uniform(),
+                            // set 1
+                            base_color_texture(gfx, data, material, &mut textures)?,
+                        ])
+                    },
+                )?;
+
+                let handle = gfx.register_drawable(&mut entry);
+                gfx.get_registry().set_transform(handle, node.transform);
+                if material.blend {
+                    gfx.get_registry()
+                        .set_layer(handle, RenderLayer::Transparent, 0);
+                }
+
+                parts.push(ModelPart {
+                    entry: entry,
+                    pc: pc,
+                    transform: node.transform,
+                });
+            }
+        }
+
+        Ok(Self {
+            parts: parts,
+            registry: gfx.get_registry().clone(),
+        })
+    }
+
+    pub fn get_handles(&self) -> Vec<DrawableHandle> {
+        self.parts
+            .iter()
+            .filter_map(|p| p.entry.get_handle())
+            .collect()
+    }
+
+    /// Places the whole model, the node transforms of the file are applied after this.
+    pub fn set_transform(&self, transform: Matrix4<f32>) {
+        apply_transform(&self.part_transforms(), &self.registry, transform);
+    }
+
+    /// The model follows `node` from the next frame on.
+    pub fn attach_to(&self, scene: &SceneGraph, node: NodeId) {
+        let parts = self.part_transforms();
+        let registry = self.registry.clone();
+        scene.attach(node, move |world| apply_transform(&parts, &registry, world));
+    }
+
+    fn part_transforms(&self) -> Vec<PartTransform> {
+        self.parts
+            .iter()
+            .map(|p| (p.pc.clone(), p.entry.get_handle(), p.transform))
+            .collect()
+    }
+}
+
+fn apply_transform(parts: &[PartTransform], registry: &DrawableRegistry, transform: Matrix4<f32>) {
+    for (pc, handle, local) in parts {
+        let model = transform * local;
+        pc.access_data(|data| data.model = model.into());
+        if let Some(handle) = handle {
+            registry.set_transform(*handle, model);
+        }
+    }
+}
+
+/// Textures are created once per image and load, primitives without one get a white texture.
+fn base_color_texture(
+    gfx: &Graphics,
+    data: &ModelData,
+    material: &MaterialData,
+    textures: &mut HashMap<Option<usize>, Arc<Texture>>,
+) -> Result<Arc<Texture>, GraphicsError> {
+    let image_index = material
+        .base_color_texture
+        .filter(|p| *p < data.images.len());
+
+    if let Some(texture) = textures.get(&image_index) {
+        return Ok(texture.clone());
+    }
+
+    let texture = match image_index.map(|p| &data.images[p]) {
+        Some(image) => {
+            Texture::from_rgba(gfx, image.width, image.height, image.pixels.clone(), 1, 0)?
+        }
+        None => Texture::white(gfx, 1, 0)?,
+    };
+    textures.insert(image_index, texture.clone());
+
+    Ok(texture)
+}
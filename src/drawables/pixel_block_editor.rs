@@ -0,0 +1,361 @@
+use std::{fmt, io, path::Path, sync::Arc};
+
+use cgmath::{Matrix4, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+use vulkano::{
+    buffer::BufferContents, pipeline::graphics::vertex_input::Vertex, shader::ShaderStages,
+};
+
+use crate::graphics::{
+    bindable::{self, DynamicIndexBuffer, DynamicVertexBuffer, PushConstant},
+    drawable::{DrawableEntry, GenericDrawable, RenderLayer, SharedKey},
+    error::GraphicsError,
+    shaders::{frag_3dColored, vert_3dColored},
+    Graphics,
+};
+use crate::input::mouse::MOUSE_LEFT;
+use crate::input::Input;
+
+use super::Grid;
+
+/// The filled cells of a block, trimmed to their bounding box.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockShape {
+    pub width: u32,
+    pub height: u32,
+    /// `[column, row]`, row 0 is the top row.
+    pub cells: Vec<[u32; 2]>,
+}
+
+#[derive(Debug)]
+pub enum BlockShapeError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for BlockShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockShapeError::Io(e) => write!(f, "could not access block shape: {e}"),
+            BlockShapeError::Parse(e) => write!(f, "could not parse block shape: {e}"),
+            BlockShapeError::Serialize(e) => write!(f, "could not serialize block shape: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for BlockShapeError {}
+
+/// Field names have to match the inputs of `3dColored.vert`.
+#[derive(BufferContents, Vertex, Clone, Copy)]
+#[repr(C)]
+struct CellVertex {
+    #[format(R32G32B32_SFLOAT)]
+    pos: [f32; 3],
+    #[format(R32G32B32_SFLOAT)]
+    color: [f32; 3],
+}
+
+impl BlockShape {
+    /// Trims `cells` to their bounding box, an empty shape if there are none.
+    pub fn from_cells(cells: &[[u32; 2]]) -> Self {
+        let Some(&[first_column, first_row]) = cells.first() else {
+            return Self::default();
+        };
+        let (min_column, min_row, max_column, max_row) = cells.iter().fold(
+            (first_column, first_row, first_column, first_row),
+            |(min_column, min_row, max_column, max_row), &[column, row]| {
+                (
+                    min_column.min(column),
+                    min_row.min(row),
+                    max_column.max(column),
+                    max_row.max(row),
+                )
+            },
+        );
+
+        Self {
+            width: max_column - min_column + 1,
+            height: max_row - min_row + 1,
+            cells: cells
+                .iter()
+                .map(|[column, row]| [column - min_column, row - min_row])
+                .collect(),
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, BlockShapeError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(BlockShapeError::Serialize)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, BlockShapeError> {
+        ron::from_str(text).map_err(BlockShapeError::Parse)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BlockShapeError> {
+        std::fs::write(path, self.to_ron()?).map_err(BlockShapeError::Io)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BlockShapeError> {
+        let text = std::fs::read_to_string(path).map_err(BlockShapeError::Io)?;
+        Self::from_ron(&text)
+    }
+}
+
+/// A grid whose cells are filled and emptied by clicking them, to draw tetris style blocks.
+/// Positions are in the screen camera's space, the same one `Mouse::cursor_position` uses.
+pub struct PixelBlockEditor {
+    grid: Grid,
+    /// Draws the filled cells, the buffers are rebuilt whenever a cell changes.
+    filled_entry: DrawableEntry,
+    filled_vertices: Arc<DynamicVertexBuffer<CellVertex>>,
+    filled_indices: Arc<DynamicIndexBuffer>,
+    /// Row by row, starting with the top row.
+    filled: Vec<bool>,
+    center: Vector2<f32>,
+    cell_width: f32,
+    /// Applies to all cells the next time one of them changes.
+    pub color: [f32; 3],
+}
+
+impl PixelBlockEditor {
+    pub fn new(
+        gfx: &mut Graphics,
+        dimensions: Vector2<u32>,
+        cell_width: f32,
+        center: Vector2<f32>,
+    ) -> Result<Self, GraphicsError> {
+        let transform: [[f32; 4]; 4] =
+            (Matrix4::from_translation(Vector3::new(center.x, center.y, 0.0))
+                * Matrix4::from_scale(cell_width))
+            .into();
+
+        let grid = Grid::new(gfx, dimensions, cell_width)?;
+        grid.pc.access_data(|data| data.transform = transform);
+
+        // the vertices are in cells, like the grid's
+        let pc = PushConstant::new(
+            gfx,
+            0,
+            vert_3dColored::Pc { model: transform },
+            ShaderStages::VERTEX,
+        );
+        let filled_vertices = DynamicVertexBuffer::new(gfx, Vec::new())?;
+        let filled_indices = DynamicIndexBuffer::new(gfx, Vec::new())?;
+
+        let mut filled_entry = GenericDrawable::new(
+            gfx,
+            SharedKey::of::<Self>(),
+            || {
+                Ok(vec![
+                    pc.clone(),
+                    filled_vertices.clone(),
+                    filled_indices.clone(),
+                ])
+            },
+            || {
+                Ok(vec![
+                    bindable::VertexShader::from_module(
+                        vert_3dColored::load(gfx.get_device()).map_err(GraphicsError::vulkan)?,
+                    ),
+                    bindable::FragmentShader::from_module(
+                        frag_3dColored::load(gfx.get_device()).map_err(GraphicsError::vulkan)?,
+                    ),
+                    gfx.get_utils().screen_camera.get_uniform(),
+                ])
+            },
+        )?;
+
+        let handle = gfx.register_drawable(&mut filled_entry);
+        // below the grid lines
+        gfx.get_registry().set_layer(handle, RenderLayer::Ui, -1);
+
+        Ok(Self {
+            grid: grid,
+            filled_entry: filled_entry,
+            filled_vertices: filled_vertices,
+            filled_indices: filled_indices,
+            filled: vec![false; (dimensions.x * dimensions.y) as usize],
+            center: center,
+            cell_width: cell_width,
+            color: [1.0, 0.6, 0.1],
+        })
+    }
+
+    /// Toggles the cell under the cursor when the left mouse button is pressed.
+    pub fn update(&mut self, input: &Input) {
+        if !input.mouse.is_button_pressed(MOUSE_LEFT) {
+            return;
+        }
+        let position = input.mouse.cursor_position.get();
+        let position = Vector2::new(position.x as f32, position.y as f32);
+        if let Some(cell) = self.cell_at(position) {
+            let filled = self.is_filled(cell);
+            self.set_filled(cell, !filled);
+        }
+    }
+
+    pub fn get_dimensions(&self) -> Vector2<u32> {
+        self.grid.dimensions
+    }
+
+    /// The `[column, row]` of the cell at `position`, `None` outside of the grid.
+    pub fn cell_at(&self, position: Vector2<f32>) -> Option<[u32; 2]> {
+        cell_in_grid(
+            self.get_dimensions(),
+            (position - self.center) / self.cell_width,
+        )
+    }
+
+    /// `false` outside of the grid.
+    pub fn is_filled(&self, cell: [u32; 2]) -> bool {
+        self.index_of(cell).is_some_and(|index| self.filled[index])
+    }
+
+    /// Does nothing outside of the grid.
+    pub fn set_filled(&mut self, cell: [u32; 2], filled: bool) {
+        if let Some(index) = self.index_of(cell) {
+            if self.filled[index] != filled {
+                self.filled[index] = filled;
+                self.rebuild();
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.filled.iter_mut().for_each(|p| *p = false);
+        self.rebuild();
+    }
+
+    pub fn get_shape(&self) -> BlockShape {
+        let cells: Vec<[u32; 2]> = self.filled_cells().collect();
+        BlockShape::from_cells(&cells)
+    }
+
+    /// Replaces the current cells, starting in the top left corner. Cells that don't fit are
+    /// left out.
+    pub fn set_shape(&mut self, shape: &BlockShape) {
+        self.filled.iter_mut().for_each(|p| *p = false);
+        for cell in &shape.cells {
+            if let Some(index) = self.index_of(*cell) {
+                self.filled[index] = true;
+            }
+        }
+        self.rebuild();
+    }
+
+    fn index_of(&self, [column, row]: [u32; 2]) -> Option<usize> {
+        let dimensions = self.get_dimensions();
+        (column < dimensions.x && row < dimensions.y)
+            .then_some((row * dimensions.x + column) as usize)
+    }
+
+    fn filled_cells(&self) -> impl Iterator<Item = [u32; 2]> + '_ {
+        let columns = self.get_dimensions().x;
+        self.filled
+            .iter()
+            .enumerate()
+            .filter(|(_, filled)| **filled)
+            .map(move |(index, _)| [index as u32 % columns, index as u32 / columns])
+    }
+
+    fn rebuild(&self) {
+        let dimensions = self.get_dimensions();
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for [column, row] in self.filled_cells() {
+            let left = column as f32 - dimensions.x as f32 / 2.0;
+            let top = dimensions.y as f32 / 2.0 - row as f32;
+            let first = vertices.len() as u32;
+            for [x, y] in [[0.0, 0.0], [1.0, 0.0], [0.0, -1.0], [1.0, -1.0]] {
+                vertices.push(CellVertex {
+                    pos: [left + x, top + y, 0.0],
+                    color: self.color,
+                });
+            }
+            indices.extend([0, 1, 3, 0, 3, 2].map(|p| first + p));
+        }
+
+        self.filled_vertices
+            .access_vertices(|current| *current = vertices);
+        self.filled_indices
+            .access_indices(|current| *current = indices);
+    }
+}
+
+/// `position` is relative to the grid's center and measured in cells.
+fn cell_in_grid(dimensions: Vector2<u32>, position: Vector2<f32>) -> Option<[u32; 2]> {
+    let column = (position.x + dimensions.x as f32 / 2.0).floor();
+    let row = (dimensions.y as f32 / 2.0 - position.y).floor();
+
+    let inside =
+        column >= 0.0 && row >= 0.0 && column < dimensions.x as f32 && row < dimensions.y as f32;
+    inside.then_some([column as u32, row as u32])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_survive_a_ron_round_trip() {
+        let shape = BlockShape {
+            width: 3,
+            height: 2,
+            cells: vec![[0, 0], [1, 0], [2, 0], [1, 1]],
+        };
+        let text = shape.to_ron().unwrap();
+        assert_eq!(BlockShape::from_ron(&text).unwrap(), shape);
+    }
+
+    #[test]
+    fn invalid_ron_is_a_parse_error() {
+        let result = BlockShape::from_ron("(width: 1");
+        assert!(matches!(result, Err(BlockShapeError::Parse(_))));
+    }
+
+    #[test]
+    fn shapes_are_trimmed_to_their_cells() {
+        let shape = BlockShape::from_cells(&[[2, 1], [3, 1], [3, 2], [3, 3]]);
+        assert_eq!(
+            shape,
+            BlockShape {
+                width: 2,
+                height: 3,
+                cells: vec![[0, 0], [1, 0], [1, 1], [1, 2]],
+            }
+        );
+    }
+
+    #[test]
+    fn no_cells_make_an_empty_shape() {
+        assert_eq!(BlockShape::from_cells(&[]), BlockShape::default());
+    }
+
+    #[test]
+    fn cells_are_counted_from_the_top_left() {
+        let dimensions = Vector2::new(4, 2);
+        assert_eq!(
+            cell_in_grid(dimensions, Vector2::new(-1.5, 0.5)),
+            Some([0, 0])
+        );
+        assert_eq!(
+            cell_in_grid(dimensions, Vector2::new(1.5, -0.5)),
+            Some([3, 1])
+        );
+        assert_eq!(
+            cell_in_grid(dimensions, Vector2::new(0.0, 0.0)),
+            Some([2, 1])
+        );
+    }
+
+    #[test]
+    fn positions_outside_of_the_grid_have_no_cell() {
+        let dimensions = Vector2::new(4, 2);
+        assert_eq!(cell_in_grid(dimensions, Vector2::new(-2.5, 0.0)), None);
+        assert_eq!(cell_in_grid(dimensions, Vector2::new(2.0, 0.0)), None);
+        assert_eq!(cell_in_grid(dimensions, Vector2::new(0.0, 1.5)), None);
+        assert_eq!(cell_in_grid(dimensions, Vector2::new(0.0, -1.0)), None);
+    }
+}
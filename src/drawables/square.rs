@@ -1,13 +1,15 @@
 use std::sync::Arc;
 
-use cgmath::Vector2;
+use cgmath::{Matrix4, Vector2};
 use vulkano::{
     buffer::BufferContents, pipeline::graphics::vertex_input::Vertex, shader::ShaderStages,
 };
 
 use crate::graphics::{
     bindable::{self, PushConstant},
-    drawable::{DrawableEntry, GenericDrawable},
+    drawable::{DrawableEntry, GenericDrawable, RenderLayer, SharedKey},
+    error::GraphicsError,
+    scene::{NodeId, SceneGraph},
     shaders::{frag_solid_white, vert_cartesian_2d},
     Graphics,
 };
@@ -15,10 +17,11 @@ use crate::graphics::{
 pub struct Square {
     entry: DrawableEntry,
     pub transform: Arc<PushConstant<vert_cartesian_2d::Data>>,
+    radius: f32,
 }
 
 impl Square {
-    pub fn new(gfx: &mut Graphics, pos: Vector2<f32>, radius: f32) -> Self {
+    pub fn new(gfx: &mut Graphics, pos: Vector2<f32>, radius: f32) -> Result<Self, GraphicsError> {
         let data = PushConstant::new(
             gfx,
             0,
@@ -35,8 +38,8 @@ impl Square {
 
         let mut entry = GenericDrawable::new(
             gfx,
-            5,
-            || vec![data.clone()],
+            SharedKey::of::<Self>(),
+            || Ok(vec![data.clone()]),
             || {
                 #[derive(BufferContents, Vertex)]
                 #[repr(C)]
@@ -54,25 +57,43 @@ impl Square {
 
                 let indices: Vec<u32> = vec![0, 3, 1, 0, 2, 3];
 
-                vec![
-                    bindable::VertexBuffer::new(gfx, vertices),
-                    bindable::IndexBuffer::new(gfx, indices),
+                Ok(vec![
+                    bindable::VertexBuffer::new(gfx, vertices)?,
+                    bindable::IndexBuffer::new(gfx, indices)?,
                     bindable::VertexShader::from_module(
-                        vert_cartesian_2d::load(gfx.get_device()).unwrap(),
+                        vert_cartesian_2d::load(gfx.get_device()).map_err(GraphicsError::vulkan)?,
                     ),
                     bindable::FragmentShader::from_module(
-                        frag_solid_white::load(gfx.get_device()).unwrap(),
+                        frag_solid_white::load(gfx.get_device()).map_err(GraphicsError::vulkan)?,
                     ),
-                    gfx.get_utils().cartesian_to_normalized.clone(),
-                ]
+                    gfx.get_utils().screen_camera.get_uniform(),
+                ])
             },
-        );
+        )?;
 
-        gfx.register_drawable(&mut entry);
+        // above the grid
+        let handle = gfx.register_drawable(&mut entry);
+        gfx.get_registry().set_layer(handle, RenderLayer::Ui, 1);
 
-        Self {
+        Ok(Self {
             entry: entry,
             transform: data,
+            radius: radius,
+        })
+    }
+
+    /// The square follows `node` from the next frame on, replacing the position it was
+    /// created with. Its radius is applied before the node's transform.
+    pub fn attach_to(&self, scene: &SceneGraph, node: NodeId) {
+        let transform = self.transform.clone();
+        let radius = self.radius;
+        scene.attach(node, move |world| {
+            transform.access_data(|data| {
+                data.transform = (world * Matrix4::from_scale(radius)).into();
+            })
+        });
+        if let Some(handle) = self.entry.get_handle() {
+            scene.attach_drawable(node, handle);
         }
     }
 }
@@ -1,9 +1,9 @@
 use std::sync::Arc;
 
-use cgmath::{SquareMatrix, Point3, Vector3, Deg};
+use cgmath::SquareMatrix;
 use vulkano::{buffer::BufferContents, pipeline::graphics::vertex_input::Vertex, shader::ShaderStages};
 
-use crate::graphics::{drawable::{GenericDrawable, DrawableEntry}, Graphics, bindable::{self, UniformBuffer, PushConstant}, shaders::{vert_first, frag_first, vert_textured, frag_textured}};
+use crate::graphics::{drawable::{GenericDrawable, DrawableEntry, SharedKey}, error::GraphicsError, Graphics, bindable::{self, PushConstant}, bounds::PositionedVertex, registry::DrawableRegistry, shaders::{vert_textured, frag_textured}};
 
 pub use vert_textured::Pc;
 pub use vert_textured::GlobalUbo;
@@ -11,21 +11,24 @@ pub use vert_textured::GlobalUbo;
 pub struct TexturedSquare
 {
     entry: DrawableEntry,
+    /// Writing the model here directly moves the square without culling knowing, use
+    /// `set_transform` instead.
     pub pc: Arc<PushConstant<Pc>>,
+    registry: DrawableRegistry,
 }
 
 impl TexturedSquare {
-    pub fn new(gfx: &mut Graphics, create_registered: bool) -> Self
+    pub fn new(gfx: &mut Graphics, create_registered: bool) -> Result<Self, GraphicsError>
     {
 
         let pc = PushConstant::new(gfx, 0, Pc {
             model: cgmath::Matrix4::identity().into(),
         }, ShaderStages::VERTEX);
 
-        let mut entry = GenericDrawable::new(&gfx, 0, || {
-            vec![
+        let mut entry = GenericDrawable::new(&gfx, SharedKey::of::<Self>(), || {
+            Ok(vec![
                 pc.clone(),
-            ]
+            ])
         }, || {
             #[derive(BufferContents, Vertex)]
             #[repr(C)]
@@ -35,6 +38,11 @@ impl TexturedSquare {
                 #[format(R32G32_SFLOAT)]
                 pub uv: [f32; 2],
             }
+            impl PositionedVertex for Vertex {
+                fn position(&self) -> [f32; 3] {
+                    [self.pos[0], self.pos[1], 0.0]
+                }
+            }
             let vertices: Vec<Vertex> = vec![
                 Vertex{pos: [-0.5,  0.5], uv: [0.0, 0.0]},
                 Vertex{pos: [-0.5, -0.5], uv: [0.0, 1.0]},
@@ -46,22 +54,32 @@ impl TexturedSquare {
                 0, 2, 3
             ];
 
-            vec![
-                bindable::VertexShader::from_module(vert_textured::load(gfx.get_device()).unwrap()),
-                bindable::FragmentShader::from_module(frag_textured::load(gfx.get_device()).unwrap()),
-                bindable::IndexBuffer::new(gfx, indices),
-                bindable::VertexBuffer::new(gfx, vertices),
-                bindable::Texture::new(gfx, "textures/batako.png", 1, 0),
-            ]
-        });
+            Ok(vec![
+                bindable::VertexShader::from_module(vert_textured::load(gfx.get_device()).map_err(GraphicsError::vulkan)?),
+                bindable::FragmentShader::from_module(frag_textured::load(gfx.get_device()).map_err(GraphicsError::vulkan)?),
+                bindable::IndexBuffer::new(gfx, indices)?,
+                bindable::VertexBuffer::new_bounded(gfx, vertices)?,
+                gfx.get_utils().camera.get_uniform(),
+                bindable::Texture::new(gfx, "textures/batako.png", 1, 0)?,
+            ])
+        })?;
 
         if create_registered {
             gfx.register_drawable(&mut entry);
         }
 
-        Self {
+        Ok(Self {
             entry: entry,
             pc: pc,
+            registry: gfx.get_registry().clone(),
+        })
+    }
+
+    pub fn set_transform(&self, transform: cgmath::Matrix4<f32>)
+    {
+        self.pc.access_data(|data| data.model = transform.into());
+        if let Some(handle) = self.entry.get_handle() {
+            self.registry.set_transform(handle, transform);
         }
     }
 }
\ No newline at end of file
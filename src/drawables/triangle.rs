@@ -1,12 +1,14 @@
 use vulkano::{buffer::BufferContents, pipeline::graphics::vertex_input::Vertex};
 
-use crate::graphics::{drawable::{GenericDrawable, DrawableEntry}, Graphics, bindable, shaders::{vert_first, frag_first}};
+use crate::graphics::{drawable::{GenericDrawable, DrawableEntry, SharedKey}, error::GraphicsError, Graphics, bindable, shaders::{vert_first, frag_first}};
 
+/// Only used as the key of the shared part, the triangle has no type of its own.
+struct Triangle;
 
-pub fn new(gfx: &mut Graphics, create_registered: bool) -> DrawableEntry
+pub fn new(gfx: &mut Graphics, create_registered: bool) -> Result<DrawableEntry, GraphicsError>
 {
-    let mut entry = GenericDrawable::new(&gfx, 0, || {
-        vec![] // no per instance bindables necessary
+    let mut entry = GenericDrawable::new(&gfx, SharedKey::of::<Triangle>(), || {
+        Ok(vec![]) // no per instance bindables necessary
     }, || {
         #[derive(BufferContents, Vertex)]
         #[repr(C)]
@@ -25,17 +27,17 @@ pub fn new(gfx: &mut Graphics, create_registered: bool) -> DrawableEntry
             0, 1, 2
         ];
 
-        vec![
-            bindable::VertexShader::from_module(vert_first::load(gfx.get_device()).unwrap()),
-            bindable::FragmentShader::from_module(frag_first::load(gfx.get_device()).unwrap()),
-            bindable::IndexBuffer::new(&gfx, indices),
-            bindable::VertexBuffer::new(&gfx, vertices),
-        ]
-    });
+        Ok(vec![
+            bindable::VertexShader::from_module(vert_first::load(gfx.get_device()).map_err(GraphicsError::vulkan)?),
+            bindable::FragmentShader::from_module(frag_first::load(gfx.get_device()).map_err(GraphicsError::vulkan)?),
+            bindable::IndexBuffer::new(&gfx, indices)?,
+            bindable::VertexBuffer::new(&gfx, vertices)?,
+        ])
+    })?;
 
     if create_registered {
         gfx.register_drawable(&mut entry);
     }
 
-    entry
+    Ok(entry)
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use vulkano::{buffer::BufferContents, pipeline::graphics::vertex_input::Vertex, shader::ShaderStages};
 
-use crate::graphics::{drawable::{GenericDrawable, DrawableEntry}, Graphics, bindable::{self, UniformBuffer}, shaders::{frag_uniform_test, vert_first}};
+use crate::graphics::{drawable::{GenericDrawable, DrawableEntry, SharedKey}, error::GraphicsError, Graphics, bindable::{self, UniformBuffer}, shaders::{frag_uniform_test, vert_first}};
 
 pub use frag_uniform_test::ubo as Ubo;
 
@@ -14,14 +14,14 @@ pub struct UboTestDrawable
 
 impl UboTestDrawable
 {
-    pub fn new(gfx: &mut Graphics, create_registered: bool) -> Self
+    pub fn new(gfx: &mut Graphics, create_registered: bool) -> Result<Self, GraphicsError>
     {
         let uniform =
-            bindable::UniformBuffer::new(gfx, 0, Ubo{ brightness: 1.0 }, ShaderStages::FRAGMENT);
+            bindable::UniformBuffer::new(gfx, 0, Ubo{ brightness: 1.0 }, ShaderStages::FRAGMENT)?;
 
-        let mut entry = GenericDrawable::new(&gfx, 0, || {
+        let mut entry = GenericDrawable::new(&gfx, SharedKey::of::<Self>(), || {
 
-            vec![ uniform.clone() ]
+            Ok(vec![ uniform.clone() ])
         }, || {
             #[derive(BufferContents, Vertex)]
             #[repr(C)]
@@ -40,21 +40,21 @@ impl UboTestDrawable
                 0, 1, 2
             ];
 
-            vec![
-                bindable::VertexShader::from_module(vert_first::load(gfx.get_device()).unwrap()),
-                bindable::FragmentShader::from_module(frag_uniform_test::load(gfx.get_device()).unwrap()),
-                bindable::IndexBuffer::new(&gfx, indices),
-                bindable::VertexBuffer::new(&gfx, vertices),
-            ]
-        });
+            Ok(vec![
+                bindable::VertexShader::from_module(vert_first::load(gfx.get_device()).map_err(GraphicsError::vulkan)?),
+                bindable::FragmentShader::from_module(frag_uniform_test::load(gfx.get_device()).map_err(GraphicsError::vulkan)?),
+                bindable::IndexBuffer::new(&gfx, indices)?,
+                bindable::VertexBuffer::new(&gfx, vertices)?,
+            ])
+        })?;
 
         if create_registered {
             gfx.register_drawable(&mut entry);
         }
         
-        Self {
+        Ok(Self {
             entry: entry,
             uniform: uniform
-        }
+        })
     }
 }
\ No newline at end of file
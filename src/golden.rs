@@ -0,0 +1,203 @@
+//! Golden image tests for the drawables.
+//!
+//! Every case renders a single drawable into a headless `Graphics` and compares the frame
+//! against a reference png in `tests/golden/`. On a mismatch the rendered frame and a diff
+//! image are written to `target/golden/`. Run with `--bless` to (re)generate the references.
+
+use std::{any::Any, path::Path};
+
+use vulkano::{format::Format, VulkanLibrary};
+
+use crate::app::drawables;
+use crate::graphics::{
+    capture::CapturedFrame, config::GraphicsConfig, error::GraphicsError, Graphics,
+};
+
+const REFERENCE_DIR: &str = "tests/golden";
+const OUTPUT_DIR: &str = "target/golden";
+
+const EXTENT: [u32; 2] = [320, 240];
+const FORMAT: Format = Format::R8G8B8A8_UNORM;
+
+/// Maximum allowed difference per color channel, software rasterizers don't always agree on
+/// the last bit.
+const CHANNEL_TOLERANCE: u8 = 2;
+
+/// Exit code of `--golden` when the tests couldn't run, the same one automake uses for skips.
+pub const SKIPPED_EXIT_CODE: i32 = 77;
+
+pub enum GoldenOutcome {
+    Passed,
+    Failed,
+    /// No Vulkan library is installed, so nothing was compared.
+    Skipped,
+}
+
+struct GoldenCase {
+    name: &'static str,
+    /// The returned value keeps the drawable alive until the frame has been captured.
+    create: fn(&mut Graphics) -> Result<Box<dyn Any>, GraphicsError>,
+}
+
+const CASES: [GoldenCase; 6] = [
+    GoldenCase {
+        name: "grid",
+        create: |gfx| {
+            drawables::Grid::new(gfx, cgmath::Vector2 { x: 5, y: 4 }, 40.0)
+                .map(|p| Box::new(p) as Box<dyn Any>)
+        },
+    },
+    GoldenCase {
+        name: "square",
+        create: |gfx| {
+            drawables::Square::new(gfx, cgmath::Vector2::new(20.0, -10.0), 30.0)
+                .map(|p| Box::new(p) as Box<dyn Any>)
+        },
+    },
+    GoldenCase {
+        name: "cube",
+        create: |gfx| drawables::Cube::new(gfx, true).map(|p| Box::new(p) as Box<dyn Any>),
+    },
+    GoldenCase {
+        name: "textured_square",
+        create: |gfx| {
+            drawables::TexturedSquare::new(gfx, true).map(|p| Box::new(p) as Box<dyn Any>)
+        },
+    },
+    GoldenCase {
+        name: "triangle",
+        create: |gfx| drawables::triangle::new(gfx, true).map(|p| Box::new(p) as Box<dyn Any>),
+    },
+    GoldenCase {
+        name: "instanced_squares",
+        create: |gfx| {
+            let instances = (0..5)
+                .map(|i| drawables::SquareInstance {
+                    offset: [i as f32 * 50.0 - 100.0, (i % 2) as f32 * 40.0 - 20.0],
+                    scale: 10.0 + i as f32 * 3.0,
+                    color: [i as f32 / 4.0, 1.0 - i as f32 / 4.0, 0.5],
+                })
+                .collect();
+            drawables::InstancedSquares::new(gfx, instances).map(|p| Box::new(p) as Box<dyn Any>)
+        },
+    },
+];
+
+/// Runs every golden case, a single failing case fails the whole run.
+pub fn run(bless: bool) -> GoldenOutcome {
+    if let Err(e) = VulkanLibrary::new() {
+        log::warn!("Skipping golden image tests, no Vulkan library is installed: {e}");
+        return GoldenOutcome::Skipped;
+    }
+
+    let output_dir = if bless { REFERENCE_DIR } else { OUTPUT_DIR };
+    if let Err(e) = std::fs::create_dir_all(output_dir) {
+        log::error!("Failed to create {output_dir}: {e}");
+        return GoldenOutcome::Failed;
+    }
+
+    let mut passed = true;
+
+    for case in CASES.iter() {
+        let frame = match render(case) {
+            Ok(frame) => frame,
+            Err(e) => {
+                log::error!("[FAIL]  {}: {e}", case.name);
+                passed = false;
+                continue;
+            }
+        };
+
+        let reference_path = Path::new(REFERENCE_DIR).join(format!("{}.png", case.name));
+
+        if bless {
+            match frame.save_png(&reference_path) {
+                Ok(_) => log::info!("[BLESS] {}", case.name),
+                Err(e) => {
+                    log::error!("[FAIL]  {}: could not write reference: {e}", case.name);
+                    passed = false;
+                }
+            }
+            continue;
+        }
+
+        let result = match CapturedFrame::load_png(&reference_path) {
+            Ok(reference) => compare(case.name, &frame, &reference),
+            Err(e) => Err(format!(
+                "could not load {}: {e} (run with --bless to create it)",
+                reference_path.display()
+            )),
+        };
+
+        match result {
+            Ok(_) => log::info!("[OK]    {}", case.name),
+            Err(reason) => {
+                log::error!("[FAIL]  {}: {reason}", case.name);
+                let actual_path = Path::new(OUTPUT_DIR).join(format!("{}.png", case.name));
+                if let Err(e) = frame.save_png(&actual_path) {
+                    log::error!("        could not write {}: {e}", actual_path.display());
+                }
+                passed = false;
+            }
+        }
+    }
+
+    if passed {
+        GoldenOutcome::Passed
+    } else {
+        GoldenOutcome::Failed
+    }
+}
+
+fn render(case: &GoldenCase) -> Result<CapturedFrame, GraphicsError> {
+    let mut gfx = Graphics::new_headless(GraphicsConfig::default(), EXTENT, FORMAT)?;
+    let _drawable = (case.create)(&mut gfx)?;
+    gfx.capture_frame()
+}
+
+/// Compares two frames pixel by pixel. On failure a diff image is written to `OUTPUT_DIR`
+/// where mismatching pixels are red and everything else is a darkened copy of the reference.
+fn compare(name: &str, actual: &CapturedFrame, reference: &CapturedFrame) -> Result<(), String> {
+    if actual.width != reference.width || actual.height != reference.height {
+        return Err(format!(
+            "size mismatch, got {}x{} but the reference is {}x{}",
+            actual.width, actual.height, reference.width, reference.height
+        ));
+    }
+
+    let mut mismatched_pixels = 0;
+    let mut diff_data = Vec::with_capacity(reference.data.len());
+
+    for (a, r) in actual
+        .data
+        .chunks_exact(4)
+        .zip(reference.data.chunks_exact(4))
+    {
+        let matches = a
+            .iter()
+            .zip(r)
+            .all(|(a, r)| a.abs_diff(*r) <= CHANNEL_TOLERANCE);
+
+        if matches {
+            diff_data.extend(r[..3].iter().map(|p| p / 4));
+            diff_data.push(255);
+        } else {
+            mismatched_pixels += 1;
+            diff_data.extend([255, 0, 0, 255]);
+        }
+    }
+
+    if mismatched_pixels == 0 {
+        return Ok(());
+    }
+
+    let diff = CapturedFrame {
+        width: reference.width,
+        height: reference.height,
+        data: diff_data,
+    };
+    let diff_path = Path::new(OUTPUT_DIR).join(format!("{name}_diff.png"));
+    _ = diff.save_png(&diff_path);
+
+    Err(format!("{mismatched_pixels} pixels differ"))
+}
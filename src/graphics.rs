@@ -1,27 +1,50 @@
 pub mod bindable;
+pub mod bounds;
+pub mod camera;
+pub mod capture;
+pub mod config;
+pub mod device;
 pub mod drawable;
+pub mod error;
+pub mod import;
+pub mod mesh;
 pub mod pipeline;
+pub mod recording;
+pub mod registry;
+pub mod scene;
 pub mod shaders;
+pub mod upload;
 pub mod utils;
 
 use std::cmp::min;
-use std::collections::HashMap;
-use std::sync::{Arc, OnceLock, Weak};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
 use vulkano::command_buffer::allocator::StandardCommandBufferAlloc;
-use vulkano::command_buffer::{PrimaryAutoCommandBuffer, RenderPassBeginInfo};
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::{
+    CopyImageToBufferInfo, PrimaryAutoCommandBuffer, RenderPassBeginInfo,
+};
 use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
 use vulkano::format::{ClearValue, FormatFeatures};
-use vulkano::image::{AttachmentImage, ImageTiling};
-use vulkano::render_pass::SubpassDependency;
-
-use self::drawable::{Drawable, DrawableEntry, DrawableSharedPart, GenericDrawable};
+use vulkano::image::{AttachmentImage, ImageAccess, ImageTiling};
+use vulkano::render_pass::{Subpass, SubpassDependency};
+
+use self::bindable::{BindContext, DirtyFlag, FrameUpdate};
+use self::camera::Camera;
+use self::capture::CapturedFrame;
+use self::config::{DebugSeverity, GraphicsConfig};
+use self::device::{DeviceInfo, DeviceReport, DeviceSelector};
+use self::error::GraphicsError;
+use self::recording::{DrawRecorder, RenderStats};
+use self::upload::{UploadBuilder, UploadHandle, UploadTarget, Uploader};
+use self::drawable::{DrawableEntry, SharedPartCache};
+use self::registry::{DrawableHandle, DrawableRegistry};
 use vulkano::sync::{AccessFlags, PipelineStages};
 use vulkano::{
     command_buffer::{
         allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
     },
     device::{
-        physical::{PhysicalDevice, PhysicalDeviceType},
+        physical::PhysicalDevice,
         Device, DeviceCreateInfo, DeviceExtensions, Features, Queue, QueueCreateInfo, QueueFlags,
     },
     format::Format,
@@ -30,10 +53,13 @@ use vulkano::{
         ImageAspects, ImageLayout, ImageSubresourceRange, ImageUsage, SampleCount, SwapchainImage,
     },
     instance::{
-        debug::{DebugUtilsMessenger, DebugUtilsMessengerCreateInfo, ValidationFeatureEnable},
+        debug::{
+            DebugUtilsMessageSeverity, DebugUtilsMessageType, DebugUtilsMessenger,
+            DebugUtilsMessengerCreateInfo, Message, ValidationFeatureEnable,
+        },
         Instance, InstanceCreateInfo, InstanceExtensions,
     },
-    memory::allocator::StandardMemoryAllocator,
+    memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator},
     pipeline::graphics::viewport::Viewport,
     render_pass::{
         AttachmentDescription, AttachmentReference, Framebuffer, FramebufferCreateInfo, LoadOp,
@@ -41,8 +67,8 @@ use vulkano::{
     },
     sampler::ComponentMapping,
     swapchain::{
-        acquire_next_image, ColorSpace, CompositeAlpha, Surface, Swapchain, SwapchainCreateInfo,
-        SwapchainPresentInfo,
+        acquire_next_image, AcquireError, ColorSpace, CompositeAlpha, Surface, Swapchain,
+        SwapchainCreateInfo, SwapchainCreationError, SwapchainPresentInfo,
     },
     sync::{self, FlushError, GpuFuture, Sharing},
     Version, VulkanLibrary,
@@ -54,18 +80,13 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
-const IN_FLIGHT_COUNT: usize = 2;
-
-// If true MAILBOX will always be used if available.
-// If false FIFO will be preferred.
-const PREFER_MAILBOX_PRESENT_MODE: bool = false;
-
 const DEVICE_EXTENSIONS: DeviceExtensions = DeviceExtensions {
     khr_swapchain: true,
     ..DeviceExtensions::empty()
 };
 
-const INSTANCE_EXTENSIONS: InstanceExtensions = InstanceExtensions {
+// Only enabled when validation is requested and the extensions are available.
+const VALIDATION_INSTANCE_EXTENSIONS: InstanceExtensions = InstanceExtensions {
     ext_validation_features: true,
     ext_debug_utils: true,
     ..InstanceExtensions::empty()
@@ -79,7 +100,8 @@ const ENABLED_FEATURES: Features = Features {
 const ENABLED_VALIDATION_FEATURES: [ValidationFeatureEnable; 1] =
     [ValidationFeatureEnable::BestPractices];
 
-const VALIDATION_LAYERS: [&str; 1] = ["VK_LAYER_KHRONOS_validation"];
+/// How often `capture_frame` redraws when the swapchain keeps going out of date.
+const CAPTURE_ATTEMPTS: usize = 3;
 
 #[derive(Default)]
 struct Queues {
@@ -96,121 +118,279 @@ struct QueueIndices {
 }
 
 impl QueueIndices {
-    fn is_complete(&self) -> bool {
+    fn is_complete(&self, needs_present: bool) -> bool {
         self.graphics_queue.is_some()
-            && self.present_queue.is_some()
+            && (self.present_queue.is_some() || !needs_present)
             && self.transfer_queue.is_some()
     }
 }
 
+/// What the main render pass draws into.
+enum RenderTarget {
+    Window {
+        surface: Arc<Surface>,
+        window: Arc<Window>,
+        swapchain: Arc<Swapchain>,
+        swapchain_images: Vec<Arc<SwapchainImage>>,
+    },
+    /// Used by headless instances, nothing is ever presented.
+    Offscreen { image: Arc<AttachmentImage> },
+}
+
 pub struct Graphics {
+    config: GraphicsConfig,
     //library: Arc<VulkanLibrary>,
-    //instance: Arc<Instance>,
-    //debug_messenger: Option<DebugUtilsMessenger>,
-    surface: Arc<Surface>,
-    window: Arc<Window>,
+    instance: Arc<Instance>,
+    debug_messenger: Option<DebugUtilsMessenger>,
+    target: RenderTarget,
     //physical_device: Arc<PhysicalDevice>,
     device: Arc<Device>,
     queues: Queues,
+    uploader: Uploader,
 
-    allocator: StandardMemoryAllocator,
+    allocator: Arc<StandardMemoryAllocator>,
     cmd_allocator: StandardCommandBufferAllocator,
     descriptor_set_allocator: StandardDescriptorSetAllocator,
 
-    swapchain: Arc<Swapchain>,
-    //swapchain_images: Vec<Arc<SwapchainImage>>,
     main_render_pass: Arc<RenderPass>,
     //depth_buffer: Vec<Arc<ImageView<AttachmentImage>>>,
     framebuffers: Vec<Arc<Framebuffer>>,
 
-    shared_data_map: HashMap<u32, Weak<DrawableSharedPart>>, // THIS SHOULD BE MOVED
-    registered_drawables: Vec<Weak<GenericDrawable>>,        // THIS SHOULD BE MOVED
+    shared_parts: SharedPartCache,
+    registry: DrawableRegistry,
 
     utils: OnceLock<utils::Utils>,
 
-    main_command_buffer: Option<PrimaryAutoCommandBuffer<StandardCommandBufferAlloc>>,
+    /// Recorded command buffers, indexed by framebuffer and in flight index.
+    command_buffers: Vec<Option<Arc<PrimaryAutoCommandBuffer<StandardCommandBufferAlloc>>>>,
+    dirty: DirtyFlag,
+    render_stats: RenderStats,
+    frame_updates: Mutex<Vec<Weak<dyn FrameUpdate>>>,
+    /// Resized along with the swapchain.
+    cameras: Mutex<Vec<Weak<Camera>>>,
+    /// When set, the next recorded frame copies its color attachment into this buffer.
+    pending_capture: Option<Subbuffer<[u8]>>,
     futures: Vec<Option<Box<dyn GpuFuture>>>,
     inflight_index: u32,
     framebuffer_index: u32,
 }
 
 impl Graphics {
-    pub fn new() -> (Graphics, EventLoop<()>) {
-        let library = VulkanLibrary::new().expect("Vulkan library is not installed.");
+    pub fn new(config: GraphicsConfig) -> Result<(Graphics, EventLoop<()>), GraphicsError> {
+        config.validate()?;
+
+        let library = VulkanLibrary::new()?;
+
+        let instance = create_instance(library.clone(), &config, true)?;
 
-        let instance = create_instance(library.clone());
+        let debug_messenger = create_debug_messenger(instance.clone(), config.debug_severity);
 
-        //let debug_messenger = create_debug_messenger(instance.clone());
+        let (event_loop, surface) = create_window(instance.clone(), &config)?;
 
-        let (event_loop, surface) = create_window(instance.clone());
+        let physical_device = create_physical_device(
+            instance.clone(),
+            Some(&surface),
+            config.device_selector().as_ref(),
+        )?;
 
-        let physical_device = create_physical_device(instance.clone(), surface.clone());
+        let (device, queues) = create_logical_device(physical_device.clone(), Some(&surface))?;
 
-        let (device, queues) = create_logical_device(physical_device.clone(), surface.clone());
+        let uploader = Uploader::new(
+            queues.transfer_queue.clone().ok_or(GraphicsError::NoSuitableDevice)?,
+            queues.graphics_queue.clone().ok_or(GraphicsError::NoSuitableDevice)?,
+        );
 
-        let memory_allocator = StandardMemoryAllocator::new_default(device.clone());
+        let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
 
         let cmd_allocator = StandardCommandBufferAllocator::new(device.clone(), Default::default());
 
         let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone());
 
-        let (swapchain, swapchain_images) = create_swapchain(device.clone(), surface.clone());
+        let (swapchain, swapchain_images) =
+            create_swapchain(device.clone(), surface.clone(), &config)?;
 
-        println!("Swapchain is using {:?} images.", swapchain.image_count());
+        log::info!("Swapchain is using {:?} images.", swapchain.image_count());
 
-        let swapchain_image_views = create_image_views(&swapchain_images, swapchain.clone());
+        let swapchain_image_views = create_image_views(&swapchain_images, swapchain.clone())?;
 
-        let (depth_buffers, depth_format) =
-            create_depth_buffer(device.clone(), swapchain.clone(), &memory_allocator);
+        let (depth_buffers, depth_format) = create_depth_buffer(
+            device.clone(),
+            swapchain.image_extent(),
+            swapchain.image_count() as usize,
+            &memory_allocator,
+        )?;
 
-        let main_render_pass =
-            create_main_render_pass(device.clone(), swapchain.image_format(), depth_format);
+        let main_render_pass = create_main_render_pass(
+            device.clone(),
+            swapchain.image_format(),
+            depth_format,
+            ImageLayout::PresentSrc,
+        )?;
 
         let framebuffers = create_framebuffers(
             &swapchain_image_views,
             main_render_pass.clone(),
             &depth_buffers,
-        );
+        )?;
 
-        let mut futures = Vec::with_capacity(IN_FLIGHT_COUNT);
-        futures.resize_with(IN_FLIGHT_COUNT, || Some(sync::now(device.clone()).boxed()));
+        let dirty = DirtyFlag::default();
+        let mut futures = Vec::with_capacity(config.in_flight_count);
+        futures.resize_with(config.in_flight_count, || {
+            Some(sync::now(device.clone()).boxed())
+        });
 
-        let window = surface.object().unwrap().clone().downcast().unwrap();
+        let window = surface
+            .object()
+            .and_then(|object| object.clone().downcast::<Window>().ok())
+            .ok_or(GraphicsError::SurfaceLost)?;
 
-        #[allow(unused_mut)]
-        let mut gfx = Graphics {
+        let gfx = Graphics {
+            config: config,
             //library: library,
-            //instance: instance,
-            //debug_messenger: None,
-            surface: surface,
-            window: window,
+            instance: instance,
+            debug_messenger: debug_messenger,
+            target: RenderTarget::Window {
+                surface: surface,
+                window: window,
+                swapchain: swapchain,
+                swapchain_images: swapchain_images,
+            },
             //physical_device: physical_device,
             device: device,
             queues: queues,
+            uploader: uploader,
+
+            allocator: memory_allocator,
+            cmd_allocator: cmd_allocator,
+            descriptor_set_allocator: descriptor_set_allocator,
+
+            main_render_pass: main_render_pass,
+            framebuffers: framebuffers,
+
+            shared_parts: SharedPartCache::default(),
+            registry: DrawableRegistry::new(dirty.clone()),
+
+            utils: OnceLock::new(),
+
+            command_buffers: Vec::new(),
+            dirty: dirty,
+            render_stats: RenderStats::default(),
+            frame_updates: Mutex::new(Vec::new()),
+            cameras: Mutex::new(Vec::new()),
+            pending_capture: None,
+            futures: futures,
+            inflight_index: 0,
+            framebuffer_index: 0,
+        };
+
+        _ = gfx.utils.set(utils::Utils::new(&gfx)?);
+
+        Ok((gfx, event_loop))
+    }
+
+    /// Creates a `Graphics` without a window, surface or swapchain.
+    /// Frames are rendered into an offscreen color attachment of the given extent and format,
+    /// which makes it possible to run the draw path on machines without a display.
+    /// Only one frame is in flight, since every frame renders into the same attachments.
+    pub fn new_headless(
+        mut config: GraphicsConfig,
+        extent: [u32; 2],
+        format: Format,
+    ) -> Result<Graphics, GraphicsError> {
+        config.validate()?;
+        // each frame has to chain on the previous one, a second slot would race on the attachments
+        config.in_flight_count = 1;
+
+        let library = VulkanLibrary::new()?;
+
+        let instance = create_instance(library.clone(), &config, false)?;
+
+        let debug_messenger = create_debug_messenger(instance.clone(), config.debug_severity);
+
+        let physical_device =
+            create_physical_device(instance.clone(), None, config.device_selector().as_ref())?;
+
+        let (device, queues) = create_logical_device(physical_device.clone(), None)?;
+
+        let uploader = Uploader::new(
+            queues.transfer_queue.clone().ok_or(GraphicsError::NoSuitableDevice)?,
+            queues.graphics_queue.clone().ok_or(GraphicsError::NoSuitableDevice)?,
+        );
+
+        let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+
+        let cmd_allocator = StandardCommandBufferAllocator::new(device.clone(), Default::default());
+
+        let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone());
+
+        let color_image = AttachmentImage::with_usage(
+            &memory_allocator,
+            extent,
+            format,
+            ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+        )
+        .map_err(GraphicsError::vulkan)?;
+
+        let color_image_views =
+            vec![ImageView::new_default(color_image.clone()).map_err(GraphicsError::vulkan)?];
+
+        let (depth_buffers, depth_format) =
+            create_depth_buffer(device.clone(), extent, 1, &memory_allocator)?;
+
+        let main_render_pass = create_main_render_pass(
+            device.clone(),
+            format,
+            depth_format,
+            ImageLayout::ColorAttachmentOptimal,
+        )?;
+
+        let framebuffers = create_framebuffers(
+            &color_image_views,
+            main_render_pass.clone(),
+            &depth_buffers,
+        )?;
+
+        let dirty = DirtyFlag::default();
+        let mut futures = Vec::with_capacity(config.in_flight_count);
+        futures.resize_with(config.in_flight_count, || {
+            Some(sync::now(device.clone()).boxed())
+        });
+
+        let gfx = Graphics {
+            config: config,
+            instance: instance,
+            debug_messenger: debug_messenger,
+            target: RenderTarget::Offscreen { image: color_image },
+            device: device,
+            queues: queues,
+            uploader: uploader,
 
             allocator: memory_allocator,
             cmd_allocator: cmd_allocator,
             descriptor_set_allocator: descriptor_set_allocator,
 
-            swapchain: swapchain,
-            //swapchain_images: swapchain_images,
             main_render_pass: main_render_pass,
             framebuffers: framebuffers,
 
-            shared_data_map: HashMap::new(),
-            registered_drawables: Vec::new(),
+            shared_parts: SharedPartCache::default(),
+            registry: DrawableRegistry::new(dirty.clone()),
 
             utils: OnceLock::new(),
 
-            main_command_buffer: None,
+            command_buffers: Vec::new(),
+            dirty: dirty,
+            render_stats: RenderStats::default(),
+            frame_updates: Mutex::new(Vec::new()),
+            cameras: Mutex::new(Vec::new()),
+            pending_capture: None,
             futures: futures,
             inflight_index: 0,
             framebuffer_index: 0,
         };
 
-        _ = gfx.utils.set(utils::Utils::new(&gfx));
+        _ = gfx.utils.set(utils::Utils::new(&gfx)?);
 
-        (gfx, event_loop)
+        Ok(gfx)
     }
 
     pub fn get_device(&self) -> Arc<Device> {
@@ -222,17 +402,100 @@ impl Graphics {
     pub fn get_allocator(&self) -> &StandardMemoryAllocator {
         &self.allocator
     }
-    pub fn get_shared_data_map(&self) -> &HashMap<u32, Weak<DrawableSharedPart>> {
-        &self.shared_data_map
+    /// For bindables that need to allocate after creation.
+    pub fn get_shared_allocator(&self) -> Arc<StandardMemoryAllocator> {
+        self.allocator.clone()
+    }
+    pub fn get_shared_parts(&self) -> &SharedPartCache {
+        &self.shared_parts
     }
     pub fn get_swapchain_format(&self) -> Format {
-        self.swapchain.image_format()
+        match &self.target {
+            RenderTarget::Window { swapchain, .. } => swapchain.image_format(),
+            RenderTarget::Offscreen { image } => image.format(),
+        }
+    }
+    /// Size of the images the main render pass draws into.
+    pub fn get_extent(&self) -> [u32; 2] {
+        match &self.target {
+            RenderTarget::Window { swapchain, .. } => swapchain.image_extent(),
+            RenderTarget::Offscreen { image } => image.dimensions().width_height(),
+        }
+    }
+    pub fn is_headless(&self) -> bool {
+        matches!(self.target, RenderTarget::Offscreen { .. })
     }
     pub fn get_descriptor_set_allocator(&self) -> &StandardDescriptorSetAllocator {
         &self.descriptor_set_allocator
     }
+    /// Panics when called on a headless instance.
     pub fn get_window(&self) -> Arc<Window> {
-        self.window.clone()
+        match &self.target {
+            RenderTarget::Window { window, .. } => window.clone(),
+            RenderTarget::Offscreen { .. } => panic!("Headless graphics has no window."),
+        }
+    }
+    /// Every physical device of the instance, including the ones the renderer can't use.
+    pub fn available_devices(&self) -> Result<Vec<DeviceInfo>, GraphicsError> {
+        let surface = match &self.target {
+            RenderTarget::Window { surface, .. } => Some(surface),
+            RenderTarget::Offscreen { .. } => None,
+        };
+        Ok(self
+            .instance
+            .enumerate_physical_devices()?
+            .enumerate()
+            .map(|(index, device)| {
+                let suitable = is_device_suitable(device.clone(), surface);
+                DeviceInfo::new(index, &device, suitable)
+            })
+            .collect())
+    }
+    pub fn device_report(&self) -> DeviceReport {
+        let physical_device = self.device.physical_device();
+        let index = self
+            .instance
+            .enumerate_physical_devices()
+            .ok()
+            .and_then(|mut devices| devices.position(|p| Arc::ptr_eq(&p, physical_device)))
+            .unwrap_or(0);
+
+        DeviceReport {
+            device: DeviceInfo::new(index, physical_device, true),
+            instance_api_version: self.instance.api_version(),
+            validation_enabled: !self.instance.enabled_layers().is_empty(),
+            graphics_queue_family: self.graphics_queue().queue_family_index(),
+            present_queue_family: self
+                .queues
+                .present_queue
+                .as_ref()
+                .map(|p| p.queue_family_index()),
+            transfer_queue_family: self
+                .queues
+                .transfer_queue
+                .as_ref()
+                .map(|p| p.queue_family_index())
+                .unwrap_or(self.graphics_queue().queue_family_index()),
+            target_format: self.get_swapchain_format(),
+            extent: self.get_extent(),
+            headless: self.is_headless(),
+        }
+    }
+    /// Records commands that write `target` into the current upload batch, which runs on the
+    /// transfer queue. `target` has to be created with exclusive sharing, its ownership passes
+    /// to the graphics queue family once the batch is done.
+    /// The batch is submitted by `flush_uploads` or at the latest by the next `draw_frame`,
+    /// which waits for it on the gpu before rendering.
+    pub fn record_upload(
+        &self,
+        target: UploadTarget,
+        record: impl FnOnce(&mut UploadBuilder) -> Result<(), GraphicsError>,
+    ) -> Result<(), GraphicsError> {
+        self.uploader.record(&self.cmd_allocator, target, record)
+    }
+    /// Submits the current upload batch without waiting for it.
+    pub fn flush_uploads(&mut self) -> Result<UploadHandle, GraphicsError> {
+        self.uploader.flush(&self.cmd_allocator)
     }
     pub fn graphics_queue(&self) -> Arc<Queue> {
         self.queues.graphics_queue.clone().unwrap()
@@ -240,8 +503,11 @@ impl Graphics {
     pub fn get_cmd_allocator(&self) -> &StandardCommandBufferAllocator {
         &self.cmd_allocator
     }
-    pub const fn get_in_flight_count(&self) -> usize {
-        IN_FLIGHT_COUNT
+    pub fn get_in_flight_count(&self) -> usize {
+        self.config.in_flight_count
+    }
+    pub fn get_config(&self) -> &GraphicsConfig {
+        &self.config
     }
     pub fn get_in_flight_index(&self) -> usize {
         self.inflight_index as usize
@@ -250,7 +516,99 @@ impl Graphics {
         self.utils.get().unwrap()
     }
 
-    pub fn recreate_command_buffer(&mut self) {
+    /// Draw calls and state changes of the most recently recorded command buffer.
+    pub fn get_render_stats(&self) -> RenderStats {
+        self.render_stats
+    }
+
+    pub fn get_dirty_flag(&self) -> DirtyFlag {
+        self.dirty.clone()
+    }
+    /// `update` is called on every frame until the bindable is dropped.
+    pub fn register_frame_update(&self, bindable: Weak<dyn FrameUpdate>) {
+        match self.frame_updates.lock() {
+            Ok(mut frame_updates) => frame_updates.push(bindable),
+            Err(e) => log::error!("Frame update list could not be locked! {e}"),
+        }
+    }
+
+    /// Cameras are registered when they're created.
+    pub fn register_camera(&self, camera: Weak<Camera>) {
+        match self.cameras.lock() {
+            Ok(mut cameras) => cameras.push(camera),
+            Err(e) => log::error!("Camera list could not be locked! {e}"),
+        }
+    }
+
+    fn live_cameras(&self) -> Vec<Arc<Camera>> {
+        match self.cameras.lock() {
+            Ok(cameras) => cameras.iter().filter_map(Weak::upgrade).collect(),
+            Err(e) => {
+                log::error!("Camera list could not be locked! {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    fn run_frame_updates(&self) {
+        let updates: Vec<Arc<dyn FrameUpdate>> = match self.frame_updates.lock() {
+            Ok(mut frame_updates) => {
+                frame_updates.retain(|weak| weak.strong_count() > 0);
+                frame_updates.iter().filter_map(|weak| weak.upgrade()).collect()
+            }
+            Err(_) => return,
+        };
+
+        let in_flight_index = self.inflight_index as usize;
+        let (early, late): (Vec<_>, Vec<_>) = updates.iter().partition(|p| p.runs_early());
+        for update in early.into_iter().chain(late) {
+            update.update(in_flight_index);
+        }
+    }
+
+    /// Returns the cached command buffer for the current framebuffer and in flight index,
+    /// recording it first if something changed since it was recorded.
+    fn current_command_buffer(
+        &mut self,
+    ) -> Result<Arc<PrimaryAutoCommandBuffer<StandardCommandBufferAlloc>>, GraphicsError> {
+        let slot_count = self.framebuffers.len() * self.get_in_flight_count();
+        if self.dirty.take() || self.command_buffers.len() != slot_count {
+            self.command_buffers.clear();
+            self.command_buffers.resize(slot_count, None);
+        }
+
+        // captures are one off, so they're never cached
+        if let Some(capture_buffer) = self.pending_capture.take() {
+            let (command_buffer, stats) = self.record_command_buffer(Some(capture_buffer))?;
+            self.render_stats = stats;
+            return Ok(command_buffer);
+        }
+
+        let slot = self.framebuffer_index as usize * self.get_in_flight_count()
+            + self.inflight_index as usize;
+
+        match &self.command_buffers[slot] {
+            Some(command_buffer) => Ok(command_buffer.clone()),
+            None => {
+                let (command_buffer, stats) = self.record_command_buffer(None)?;
+                self.command_buffers[slot] = Some(command_buffer.clone());
+                log::trace!("Recorded command buffer for slot {slot}: {stats:?}");
+                self.render_stats = stats;
+                Ok(command_buffer)
+            }
+        }
+    }
+
+    fn record_command_buffer(
+        &self,
+        capture_buffer: Option<Subbuffer<[u8]>>,
+    ) -> Result<
+        (
+            Arc<PrimaryAutoCommandBuffer<StandardCommandBufferAlloc>>,
+            RenderStats,
+        ),
+        GraphicsError,
+    > {
         let mut builder = AutoCommandBufferBuilder::primary(
             &self.cmd_allocator,
             self.queues
@@ -258,13 +616,13 @@ impl Graphics {
                 .as_ref()
                 .unwrap()
                 .queue_family_index(),
-            CommandBufferUsage::OneTimeSubmit,
+            CommandBufferUsage::SimultaneousUse,
         )
-        .unwrap();
+        .map_err(GraphicsError::vulkan)?;
 
         let viewport = Viewport {
             origin: [0.0, 0.0],
-            dimensions: self.swapchain.image_extent().map(|int| int as f32),
+            dimensions: self.get_extent().map(|int| int as f32),
             depth_range: 0.0..1.0,
         };
 
@@ -273,120 +631,289 @@ impl Graphics {
                 RenderPassBeginInfo {
                     render_pass: self.main_render_pass.clone(),
                     clear_values: vec![
-                        Some(ClearValue::Float([0.0, 0.0, 0.0, 1.0])),
+                        Some(ClearValue::Float(self.config.clear_color)),
                         Some(ClearValue::Depth(1.0)),
                     ],
                     ..RenderPassBeginInfo::framebuffer(
                         self.framebuffers[self.framebuffer_index as usize].clone(),
                     )
                 },
-                vulkano::command_buffer::SubpassContents::Inline,
+                vulkano::command_buffer::SubpassContents::SecondaryCommandBuffers,
             )
-            .unwrap()
-            .set_viewport(0, [viewport.clone()]);
+            .map_err(GraphicsError::vulkan)?;
+
+        let (drawables, culled) = self
+            .registry
+            .draw_items(&self.live_cameras(), &self.get_utils().camera)?;
+
+        let recorder = DrawRecorder {
+            allocator: &self.cmd_allocator,
+            queue_family_index: self.graphics_queue().queue_family_index(),
+            subpass: Subpass::from(self.main_render_pass.clone(), 0).unwrap(),
+            framebuffer: self.framebuffers[self.framebuffer_index as usize].clone(),
+            viewport: viewport,
+            ctx: BindContext {
+                in_flight_index: self.inflight_index as usize,
+            },
+        };
 
-        for drawable in self.registered_drawables.iter().filter_map(|p| p.upgrade()) {
-            for bindable in drawable.get_bindables() {
-                bindable.bind(&self, &mut builder, drawable.get_pipeline_layout());
-            }
+        let (secondaries, mut stats) = recorder.record(drawables, self.config.recording_threads)?;
+        stats.culled = culled;
+        for secondary in secondaries {
+            builder
+                .execute_commands(secondary)
+                .map_err(GraphicsError::vulkan)?;
+        }
 
-            for bindable in drawable.get_shared_bindables() {
-                bindable.bind(&self, &mut builder, drawable.get_pipeline_layout());
-            }
+        builder.end_render_pass().map_err(GraphicsError::vulkan)?;
 
-            builder.bind_pipeline_graphics(drawable.get_pipeline());
+        if let Some(capture_buffer) = capture_buffer {
+            let copy_info = match &self.target {
+                RenderTarget::Window {
+                    swapchain_images, ..
+                } => CopyImageToBufferInfo::image_buffer(
+                    swapchain_images[self.framebuffer_index as usize].clone(),
+                    capture_buffer,
+                ),
+                RenderTarget::Offscreen { image } => {
+                    CopyImageToBufferInfo::image_buffer(image.clone(), capture_buffer)
+                }
+            };
             builder
-                .draw_indexed(drawable.get_index_count(), 1, 0, 0, 0)
-                .unwrap();
+                .copy_image_to_buffer(copy_info)
+                .map_err(GraphicsError::vulkan)?;
         }
 
-        builder.end_render_pass().unwrap();
-        self.main_command_buffer = Some(builder.build().unwrap());
+        Ok((
+            Arc::new(builder.build().map_err(GraphicsError::vulkan)?),
+            stats,
+        ))
     }
 
-    pub fn draw_frame(&mut self) {
+    /// Renders a frame and reads its color attachment back to the cpu.
+    /// Blocks until the gpu has finished rendering the captured frame.
+    pub fn capture_frame(&mut self) -> Result<CapturedFrame, GraphicsError> {
+        let [width, height] = self.get_extent();
+        let format = self.get_swapchain_format();
+
+        // checked up front, a failed copy would only show up when the frame is recorded
+        if !CapturedFrame::supports_format(format) {
+            return Err(GraphicsError::UnsupportedFormat(format));
+        }
+        let copyable = match &self.target {
+            RenderTarget::Window { swapchain, .. } => {
+                swapchain.image_usage().intersects(ImageUsage::TRANSFER_SRC)
+            }
+            RenderTarget::Offscreen { image } => {
+                image.inner().image.usage().intersects(ImageUsage::TRANSFER_SRC)
+            }
+        };
+        if !copyable {
+            return Err(GraphicsError::CaptureUnsupported);
+        }
+
+        let capture_buffer = Buffer::new_slice::<u8>(
+            &self.allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Download,
+                ..Default::default()
+            },
+            (width * height * 4) as u64,
+        )?;
+
+        // a recreated swapchain doesn't submit anything, in which case the capture is retried
+        let mut submitted = false;
+        for _ in 0..CAPTURE_ATTEMPTS {
+            // the copy is recorded at the end of the next command buffer
+            self.pending_capture = Some(capture_buffer.clone());
+            let result = self.submit_frame();
+            self.pending_capture = None;
+            if result? {
+                submitted = true;
+                break;
+            }
+        }
+        if !submitted {
+            return Err(GraphicsError::SwapchainOutOfDate);
+        }
+
+        let in_flight_count = self.get_in_flight_count();
+        let submitted_index = (self.inflight_index as usize + in_flight_count - 1) % in_flight_count;
+        let submitted_future = self.futures[submitted_index]
+            .take()
+            .unwrap()
+            .then_signal_fence_and_flush();
+        self.futures[submitted_index] = Some(sync::now(self.device.clone()).boxed());
+
+        submitted_future?.wait(None)?;
+
+        let data = capture_buffer
+            .read()
+            .map_err(GraphicsError::vulkan)?
+            .to_vec();
+        CapturedFrame::from_raw(width, height, format, data)
+    }
+
+    /// An out of date swapchain is recreated and isn't reported as an error.
+    pub fn draw_frame(&mut self) -> Result<(), GraphicsError> {
+        self.submit_frame().map(|_| ())
+    }
+
+    /// Returns false if no frame was submitted because the swapchain had to be recreated.
+    fn submit_frame(&mut self) -> Result<bool, GraphicsError> {
         self.futures[self.inflight_index as usize]
             .as_mut()
             .unwrap()
             .cleanup_finished();
 
+        let swapchain = match &self.target {
+            RenderTarget::Window { swapchain, .. } => swapchain.clone(),
+            RenderTarget::Offscreen { .. } => {
+                let result = self.draw_frame_offscreen();
+                self.inflight_index =
+                    (self.inflight_index + 1) % self.config.in_flight_count as u32;
+                return result.map(|_| true);
+            }
+        };
+
         let (image_index, suboptimal, acquire_future) =
-            acquire_next_image(self.swapchain.clone(), None).unwrap();
+            match acquire_next_image(swapchain.clone(), None) {
+                Ok(acquired) => acquired,
+                Err(AcquireError::OutOfDate) => return self.recreate_swapchain().map(|_| false),
+                Err(e) => return Err(e.into()),
+            };
 
         self.framebuffer_index = image_index;
 
-        match self.main_command_buffer.as_ref() {
-            None => self.recreate_command_buffer(),
-            _ => {}
-        };
+        self.run_frame_updates();
+        let command_buffer = self.current_command_buffer()?;
 
-        let new_future = self.futures[self.inflight_index as usize]
-            .take()
-            .unwrap()
+        let new_future = self
+            .previous_frame_future()?
             .join(acquire_future)
             .then_execute(
                 self.queues.graphics_queue.clone().unwrap(),
-                self.main_command_buffer.take().unwrap(),
+                command_buffer,
             )
-            .unwrap()
+            .map_err(GraphicsError::vulkan)?
             .then_swapchain_present(
                 self.queues.graphics_queue.clone().unwrap(),
-                SwapchainPresentInfo::swapchain_image_index(self.swapchain.clone(), image_index),
+                SwapchainPresentInfo::swapchain_image_index(swapchain, image_index),
             )
             .then_signal_fence_and_flush();
 
-        match new_future {
+        let result = match new_future {
             Ok(future) => {
                 self.futures[self.inflight_index as usize] = Some(future.boxed());
+                Ok(true)
             }
             Err(FlushError::OutOfDate) => {
-                self.recreate_swapchain();
                 self.futures[self.inflight_index as usize] =
                     Some(sync::now(self.device.clone()).boxed());
+                self.recreate_swapchain().map(|_| false)
             }
             Err(e) => {
-                println!("failed to flush future: {e}");
                 self.futures[self.inflight_index as usize] =
                     Some(sync::now(self.device.clone()).boxed());
+                Err(e.into())
             }
         };
+        self.inflight_index = (self.inflight_index + 1) % self.config.in_flight_count as u32;
+        let submitted = result?;
 
         if suboptimal {
-            self.recreate_swapchain();
+            self.recreate_swapchain()?;
         }
-        self.inflight_index = (self.inflight_index + 1) % IN_FLIGHT_COUNT as u32;
+        Ok(submitted)
     }
 
-    pub fn register_drawable(&mut self, drawable_entry: &mut DrawableEntry) {
-        if drawable_entry.registered_uid.is_some() {
-            return;
+    fn draw_frame_offscreen(&mut self) -> Result<(), GraphicsError> {
+        self.framebuffer_index = 0;
+
+        self.run_frame_updates();
+        let command_buffer = self.current_command_buffer()?;
+
+        let new_future = self
+            .previous_frame_future()?
+            .then_execute(
+                self.queues.graphics_queue.clone().unwrap(),
+                command_buffer,
+            )
+            .map_err(GraphicsError::vulkan)?
+            .then_signal_fence_and_flush();
+
+        match new_future {
+            Ok(future) => {
+                self.futures[self.inflight_index as usize] = Some(future.boxed());
+                Ok(())
+            }
+            Err(e) => {
+                self.futures[self.inflight_index as usize] =
+                    Some(sync::now(self.device.clone()).boxed());
+                Err(e.into())
+            }
+        }
+    }
+
+    /// The future of the last frame on the current in flight index, joined with every pending
+    /// upload so that the next submission waits for them.
+    fn previous_frame_future(&mut self) -> Result<Box<dyn GpuFuture>, GraphicsError> {
+        let mut future = self.futures[self.inflight_index as usize].take().unwrap();
+        for upload in self.uploader.take_pending(&self.cmd_allocator)? {
+            future = future.join(upload).boxed();
+        }
+        Ok(future)
+    }
+
+    /// The entry stays registered until it's unregistered or dropped.
+    /// Registering an entry twice returns the existing handle.
+    pub fn register_drawable(&mut self, drawable_entry: &mut DrawableEntry) -> DrawableHandle {
+        if let Some(handle) = drawable_entry.get_handle() {
+            return handle;
         }
 
-        drawable_entry.registered_uid = Some(self.registered_drawables.len() as u32);
-        self.registered_drawables.push(drawable_entry.get_weak());
+        let registration = self.registry.register(drawable_entry.get_arc());
+        let handle = registration.get_handle();
+        drawable_entry.registration = Some(registration);
+        handle
     }
 
     pub fn unregister_drawable(&mut self, drawable_entry: &mut DrawableEntry) {
-        match drawable_entry.registered_uid {
-            Some(idx) => match self.registered_drawables.get_mut(idx as usize) {
-                Some(weak) => *weak = Weak::new(),
-                None => _ = dbg!("[WARN] Tried to unregister an entry that was out of bounds."),
-            },
-            None => _ = dbg!("[WARN] Tried to unregister an entry that wasn't registered."),
+        if drawable_entry.registration.take().is_none() {
+            log::warn!("Tried to unregister an entry that wasn't registered.");
         }
     }
 
-    pub fn recreate_swapchain(&mut self) {
+    pub fn get_registry(&self) -> &DrawableRegistry {
+        &self.registry
+    }
+
+    /// Does nothing for headless instances since the offscreen target never changes size.
+    pub fn recreate_swapchain(&mut self) -> Result<(), GraphicsError> {
+        let (surface, old_swapchain) = match &self.target {
+            RenderTarget::Window {
+                surface, swapchain, ..
+            } => (surface.clone(), swapchain.clone()),
+            RenderTarget::Offscreen { .. } => return Ok(()),
+        };
+
         let capabilities = self
             .device
             .physical_device()
-            .surface_capabilities(self.surface.as_ref(), Default::default())
-            .unwrap();
+            .surface_capabilities(surface.as_ref(), Default::default())
+            .map_err(GraphicsError::vulkan)?;
 
         let extent: [u32; 2] = match capabilities.current_extent {
             Some(current) => current,
             None => {
-                let window: &Window = self.surface.object().unwrap().downcast_ref().unwrap();
+                let window: &Window = surface
+                    .object()
+                    .and_then(|object| object.downcast_ref())
+                    .ok_or(GraphicsError::SurfaceLost)?;
                 let framebuffer_extent = window.inner_size();
                 let width = framebuffer_extent.width;
                 let height = framebuffer_extent.height;
@@ -405,108 +932,232 @@ impl Graphics {
 
         let create_info = SwapchainCreateInfo {
             image_extent: extent,
-            ..self.swapchain.create_info()
+            ..old_swapchain.create_info()
         };
 
-        let (swapchain, swapchain_images) = self.swapchain.recreate(create_info).unwrap();
+        let (swapchain, new_images) = old_swapchain
+            .recreate(create_info)
+            .map_err(map_swapchain_error)?;
 
-        let image_views = create_image_views(&swapchain_images, swapchain.clone());
+        let image_views = create_image_views(&new_images, swapchain.clone())?;
 
-        let (depth_buffers, _) =
-            create_depth_buffer(self.device.clone(), self.swapchain.clone(), &self.allocator);
+        let (depth_buffers, _) = create_depth_buffer(
+            self.device.clone(),
+            swapchain.image_extent(),
+            swapchain.image_count() as usize,
+            &self.allocator,
+        )?;
 
         let framebuffers =
-            create_framebuffers(&image_views, self.main_render_pass.clone(), &depth_buffers);
+            create_framebuffers(&image_views, self.main_render_pass.clone(), &depth_buffers)?;
 
-        self.swapchain = swapchain;
+        if let RenderTarget::Window {
+            swapchain: current,
+            swapchain_images,
+            ..
+        } = &mut self.target
+        {
+            *current = swapchain;
+            *swapchain_images = new_images;
+        }
         self.framebuffers = framebuffers;
-
-        self.utils.get().unwrap().recreate(&self);
+        self.dirty.mark();
+
+        let extent = self.get_extent();
+        if let Ok(mut cameras) = self.cameras.lock() {
+            cameras.retain(|weak| match weak.upgrade() {
+                Some(camera) => {
+                    camera.resize(extent);
+                    true
+                }
+                None => false,
+            });
+        }
+        Ok(())
     }
 }
 
-fn create_instance(library: Arc<VulkanLibrary>) -> Arc<Instance> {
-    let required_extensions = vulkano_win::required_extensions(&library);
+fn create_instance(
+    library: Arc<VulkanLibrary>,
+    config: &GraphicsConfig,
+    windowed: bool,
+) -> Result<Arc<Instance>, GraphicsError> {
+    let required_extensions = if windowed {
+        vulkano_win::required_extensions(&library)
+    } else {
+        InstanceExtensions::empty()
+    };
+
+    let enabled_layers = if config.validation_enabled() {
+        select_validation_layers(&library, config)
+    } else {
+        Vec::new()
+    };
+
+    // the validation features extension is provided by the validation layer itself
+    let validation_extensions = library
+        .supported_extensions_with_layers(enabled_layers.iter().map(String::as_str))
+        .map(|supported| supported.intersection(&VALIDATION_INSTANCE_EXTENSIONS))
+        .unwrap_or(InstanceExtensions::empty());
+
+    let enabled_validation_features = if validation_extensions.ext_validation_features {
+        Vec::from(ENABLED_VALIDATION_FEATURES)
+    } else {
+        Vec::new()
+    };
 
     let create_info = InstanceCreateInfo {
-        application_name: Some(String::from("Rosten")),
-        enabled_extensions: required_extensions.union(&INSTANCE_EXTENSIONS),
-        enabled_layers: VALIDATION_LAYERS.iter().map(|p| String::from(*p)).collect(),
+        application_name: Some(config.application_name.clone()),
+        enabled_extensions: required_extensions.union(&validation_extensions),
+        enabled_layers: enabled_layers,
         enumerate_portability: false,
         max_api_version: None,
-        enabled_validation_features: Vec::from_iter(ENABLED_VALIDATION_FEATURES.into_iter()),
+        enabled_validation_features: enabled_validation_features,
         ..InstanceCreateInfo::default()
     };
 
-    Instance::new(library.clone(), create_info).expect("Failed to create instance!")
+    Instance::new(library.clone(), create_info).map_err(GraphicsError::vulkan)
 }
 
-fn create_debug_messenger(instance: Arc<Instance>) -> DebugUtilsMessenger {
-    unsafe {
+/// Returns the configured validation layers that are actually installed.
+fn select_validation_layers(library: &VulkanLibrary, config: &GraphicsConfig) -> Vec<String> {
+    let available: Vec<String> = match library.layer_properties() {
+        Ok(layers) => layers.map(|p| p.name().to_owned()).collect(),
+        Err(e) => {
+            log::warn!("Could not query instance layers, validation is disabled: {e}");
+            return Vec::new();
+        }
+    };
+
+    config
+        .validation_layers
+        .iter()
+        .filter(|layer| {
+            let installed = available.contains(layer);
+            if !installed {
+                log::warn!("Validation layer {layer} is not installed, skipping it.");
+            }
+            installed
+        })
+        .cloned()
+        .collect()
+}
+
+/// Returns None if the debug utils extension isn't enabled, which is the case without validation.
+fn create_debug_messenger(
+    instance: Arc<Instance>,
+    min_severity: DebugSeverity,
+) -> Option<DebugUtilsMessenger> {
+    if !instance.enabled_extensions().ext_debug_utils {
+        return None;
+    }
+
+    let mut message_severity = DebugUtilsMessageSeverity::ERROR;
+    if min_severity >= DebugSeverity::Warning {
+        message_severity |= DebugUtilsMessageSeverity::WARNING;
+    }
+    if min_severity >= DebugSeverity::Info {
+        message_severity |= DebugUtilsMessageSeverity::INFO;
+    }
+    if min_severity >= DebugSeverity::Verbose {
+        message_severity |= DebugUtilsMessageSeverity::VERBOSE;
+    }
+
+    let messenger = unsafe {
         DebugUtilsMessenger::new(
             instance,
-            DebugUtilsMessengerCreateInfo::user_callback(Arc::new(
-                |msg: &vulkano::instance::debug::Message<'_>| {
-                    println!("DEBUG MESSENGER!!!! {}", msg.description);
-                },
-            )),
+            DebugUtilsMessengerCreateInfo {
+                message_severity: message_severity,
+                message_type: DebugUtilsMessageType::GENERAL
+                    | DebugUtilsMessageType::VALIDATION
+                    | DebugUtilsMessageType::PERFORMANCE,
+                ..DebugUtilsMessengerCreateInfo::user_callback(Arc::new(log_debug_message))
+            },
         )
-        .unwrap()
+    };
+
+    match messenger {
+        Ok(messenger) => Some(messenger),
+        Err(e) => {
+            log::warn!("Failed to create debug messenger: {e}");
+            None
+        }
     }
 }
 
-fn create_window(instance: Arc<Instance>) -> (EventLoop<()>, Arc<Surface>) {
+fn log_debug_message(msg: &Message<'_>) {
+    let level = if msg.severity.intersects(DebugUtilsMessageSeverity::ERROR) {
+        log::Level::Error
+    } else if msg.severity.intersects(DebugUtilsMessageSeverity::WARNING) {
+        log::Level::Warn
+    } else if msg.severity.intersects(DebugUtilsMessageSeverity::INFO) {
+        log::Level::Info
+    } else {
+        log::Level::Trace
+    };
+
+    log::log!(
+        target: "vulkan",
+        level,
+        "[{}] {}",
+        msg.layer_prefix.unwrap_or("unknown"),
+        msg.description
+    );
+}
+
+fn create_window(
+    instance: Arc<Instance>,
+    config: &GraphicsConfig,
+) -> Result<(EventLoop<()>, Arc<Surface>), GraphicsError> {
     let event_loop = EventLoop::new();
     let surface = WindowBuilder::new()
-        .with_inner_size(LogicalSize::new(600, 400))
+        .with_inner_size(LogicalSize::new(config.window_size[0], config.window_size[1]))
         .with_resizable(true)
-        .with_title("Batako")
+        .with_title(config.window_title.as_str())
         .build_vk_surface(&event_loop, instance.clone())
-        .expect("Failed to create window surface!");
-    (event_loop, surface)
+        .map_err(GraphicsError::vulkan)?;
+    Ok((event_loop, surface))
 }
 
-fn create_physical_device(instance: Arc<Instance>, surface: Arc<Surface>) -> Arc<PhysicalDevice> {
-    let physical_device = instance
-        .enumerate_physical_devices()
-        .expect("No appropriate physical device found!")
-        .filter(|p| is_device_suitable(p.clone(), surface.clone()))
-        .min_by_key(|p| {
-            // We assign a lower score to device types that are likely to be faster/better.
-            match p.properties().device_type {
-                PhysicalDeviceType::DiscreteGpu => 0,
-                PhysicalDeviceType::IntegratedGpu => 1,
-                PhysicalDeviceType::VirtualGpu => 2,
-                PhysicalDeviceType::Cpu => 3,
-                PhysicalDeviceType::Other => 4,
-                _ => 5,
-            }
-        })
-        .expect("no suitable physical device found");
-
-    // Some little debug infos.
-    println!(
-        "Using device: {} (type: {:?})",
-        physical_device.properties().device_name,
-        physical_device.properties().device_type,
-    );
+fn create_physical_device(
+    instance: Arc<Instance>,
+    surface: Option<&Arc<Surface>>,
+    selector: Option<&DeviceSelector>,
+) -> Result<Arc<PhysicalDevice>, GraphicsError> {
+    let devices = instance.enumerate_physical_devices()?.collect();
+    device::select_physical_device(devices, surface, selector, is_device_suitable)
+}
 
-    physical_device
+/// Headless instances don't present, so they don't need the swapchain extension.
+fn required_device_extensions(windowed: bool) -> DeviceExtensions {
+    if windowed {
+        DEVICE_EXTENSIONS
+    } else {
+        DeviceExtensions::empty()
+    }
 }
 
-fn is_device_suitable(physical_device: Arc<PhysicalDevice>, surface: Arc<Surface>) -> bool {
+fn is_device_suitable(
+    physical_device: Arc<PhysicalDevice>,
+    surface: Option<&Arc<Surface>>,
+) -> bool {
     (physical_device.api_version() >= Version::V1_3
         || physical_device.supported_extensions().khr_dynamic_rendering)
         && physical_device
             .supported_extensions()
-            .contains(&DEVICE_EXTENSIONS)
+            .contains(&required_device_extensions(surface.is_some()))
         && {
-            let indices = find_queue_indices(physical_device.clone(), surface.clone());
-            indices.graphics_queue.is_some() && indices.present_queue.is_some()
+            let indices = find_queue_indices(physical_device.clone(), surface);
+            indices.graphics_queue.is_some()
+                && (indices.present_queue.is_some() || surface.is_none())
         }
 }
 
-fn find_queue_indices(physical_device: Arc<PhysicalDevice>, surface: Arc<Surface>) -> QueueIndices {
+/// Present queue selection is skipped when no surface is given.
+fn find_queue_indices(
+    physical_device: Arc<PhysicalDevice>,
+    surface: Option<&Arc<Surface>>,
+) -> QueueIndices {
     let mut indices = QueueIndices::default();
 
     for (i, properties) in physical_device.queue_family_properties().iter().enumerate() {
@@ -514,12 +1165,14 @@ fn find_queue_indices(physical_device: Arc<PhysicalDevice>, surface: Arc<Surface
         if indices.graphics_queue.is_none() && flags.contains(QueueFlags::GRAPHICS) {
             indices.graphics_queue = Some(i as u32);
         }
-        if indices.present_queue.is_none()
-            && physical_device
-                .surface_support(i as u32, &surface)
-                .unwrap_or(false)
-        {
-            indices.present_queue = Some(i as u32);
+        if let Some(surface) = surface {
+            if indices.present_queue.is_none()
+                && physical_device
+                    .surface_support(i as u32, surface)
+                    .unwrap_or(false)
+            {
+                indices.present_queue = Some(i as u32);
+            }
         }
         if indices.transfer_queue.is_none()
             && flags.contains(QueueFlags::TRANSFER)
@@ -527,7 +1180,7 @@ fn find_queue_indices(physical_device: Arc<PhysicalDevice>, surface: Arc<Surface
         {
             indices.transfer_queue = Some(i as u32);
         }
-        if indices.is_complete() {
+        if indices.is_complete(surface.is_some()) {
             break;
         }
     }
@@ -536,21 +1189,25 @@ fn find_queue_indices(physical_device: Arc<PhysicalDevice>, surface: Arc<Surface
 
 fn create_logical_device(
     physical_device: Arc<PhysicalDevice>,
-    surface: Arc<Surface>,
-) -> (Arc<Device>, Queues) {
-    let mut extensions = DEVICE_EXTENSIONS.clone();
+    surface: Option<&Arc<Surface>>,
+) -> Result<(Arc<Device>, Queues), GraphicsError> {
+    let mut extensions = required_device_extensions(surface.is_some());
 
     if physical_device.api_version() < Version::V1_3 {
         extensions.khr_dynamic_rendering = true;
     }
 
-    let indices = find_queue_indices(physical_device.clone(), surface.clone());
+    let indices = find_queue_indices(physical_device.clone(), surface);
     let mut index_set = vec![indices.graphics_queue.unwrap()];
 
-    if !index_set.contains(&indices.present_queue.unwrap()) {
-        index_set.push(indices.present_queue.unwrap());
+    if let Some(present_queue) = indices.present_queue {
+        if !index_set.contains(&present_queue) {
+            index_set.push(present_queue);
+        }
     }
 
+    let queues_before_transfer = index_set.len();
+
     if indices.transfer_queue.is_some() && !index_set.contains(&indices.transfer_queue.unwrap()) {
         index_set.push(indices.transfer_queue.unwrap());
     }
@@ -568,47 +1225,50 @@ fn create_logical_device(
         ..Default::default()
     };
 
-    let (device, mut queue_iter) = Device::new(physical_device.clone(), create_info)
-        .expect("Failed to create logical device!");
+    let (device, mut queue_iter) =
+        Device::new(physical_device.clone(), create_info).map_err(GraphicsError::vulkan)?;
 
     let mut queues = Queues::default();
 
     queues.graphics_queue = queue_iter.next();
 
-    if !index_set.contains(&indices.present_queue.unwrap()) {
-        dbg!("Forced to use a dedicated present queue,");
-        queues.present_queue = queue_iter.next();
-    } else {
-        queues.present_queue = queues.graphics_queue.clone();
+    match indices.present_queue {
+        None => queues.present_queue = None,
+        Some(present_queue) if present_queue != indices.graphics_queue.unwrap() => {
+            log::info!("Forced to use a dedicated present queue.");
+            queues.present_queue = queue_iter.next();
+        }
+        Some(_) => queues.present_queue = queues.graphics_queue.clone(),
     }
 
-    if indices.transfer_queue.is_some() && !index_set.contains(&indices.transfer_queue.unwrap()) {
-        dbg!("Found support for dedicated transfer queue.");
+    if indices.transfer_queue.is_some() && index_set.len() > queues_before_transfer {
+        log::info!("Found support for dedicated transfer queue.");
         queues.transfer_queue = queue_iter.next();
     } else {
         queues.transfer_queue = queues.graphics_queue.clone();
     }
 
-    (device, queues)
+    Ok((device, queues))
 }
 
 fn create_swapchain(
     device: Arc<Device>,
     surface: Arc<Surface>,
-) -> (Arc<Swapchain>, Vec<Arc<SwapchainImage>>) {
+    config: &GraphicsConfig,
+) -> Result<(Arc<Swapchain>, Vec<Arc<SwapchainImage>>), GraphicsError> {
     let (capabilities, formats, present_modes) = (
         device
             .physical_device()
             .surface_capabilities(surface.as_ref(), Default::default())
-            .unwrap(),
+            .map_err(GraphicsError::vulkan)?,
         device
             .physical_device()
             .surface_formats(surface.as_ref(), Default::default())
-            .unwrap(),
+            .map_err(GraphicsError::vulkan)?,
         device
             .physical_device()
             .surface_present_modes(surface.as_ref())
-            .unwrap(),
+            .map_err(GraphicsError::vulkan)?,
     );
 
     let surface_format = formats
@@ -616,12 +1276,16 @@ fn create_swapchain(
         .find(|(format, color_space)| {
             *format == Format::B8G8R8A8_SRGB && *color_space == ColorSpace::SrgbNonLinear
         })
-        .unwrap_or(formats.first().unwrap());
+        .or(formats.first())
+        .ok_or(GraphicsError::SurfaceLost)?;
 
     let extent: [u32; 2] = match capabilities.current_extent {
         Some(current) => current,
         None => {
-            let window: &Window = surface.object().unwrap().downcast_ref().unwrap();
+            let window: &Window = surface
+                .object()
+                .and_then(|object| object.downcast_ref())
+                .ok_or(GraphicsError::SurfaceLost)?;
             let framebuffer_extent = window.inner_size();
             let width = framebuffer_extent.width;
             let height = framebuffer_extent.height;
@@ -642,7 +1306,7 @@ fn create_swapchain(
     let present_mode = present_modes
         .min_by_key(|p| match *p {
             PresentMode::Mailbox => {
-                if PREFER_MAILBOX_PRESENT_MODE {
+                if config.prefer_mailbox_present_mode {
                     0
                 } else {
                     2
@@ -654,9 +1318,9 @@ fn create_swapchain(
             PresentMode::Immediate => 4,
             _ => 5,
         })
-        .unwrap();
+        .ok_or(GraphicsError::SurfaceLost)?;
 
-    let indices = find_queue_indices(device.physical_device().clone(), surface.clone());
+    let indices = find_queue_indices(device.physical_device().clone(), Some(&surface));
     let image_sharing = if indices.graphics_queue == indices.present_queue {
         Sharing::Exclusive
     } else {
@@ -675,7 +1339,9 @@ fn create_swapchain(
         image_color_space: surface_format.1,
         image_extent: extent,
         image_array_layers: 1,
-        image_usage: ImageUsage::COLOR_ATTACHMENT,
+        // TRANSFER_SRC is needed for frame capture, but not every surface supports it
+        image_usage: ImageUsage::COLOR_ATTACHMENT
+            | (capabilities.supported_usage_flags & ImageUsage::TRANSFER_SRC),
         image_sharing: image_sharing,
         pre_transform: capabilities.current_transform,
         composite_alpha: capabilities
@@ -685,20 +1351,28 @@ fn create_swapchain(
                 CompositeAlpha::Opaque => 0,
                 _ => 1,
             })
-            .unwrap(),
+            .ok_or(GraphicsError::SurfaceLost)?,
         present_mode: present_mode,
         clipped: true,
         ..Default::default()
     };
 
-    Swapchain::new(device.clone(), surface.clone(), create_info)
-        .expect("Failed to create Swapchain!")
+    Swapchain::new(device.clone(), surface.clone(), create_info).map_err(map_swapchain_error)
+}
+
+fn map_swapchain_error(e: SwapchainCreationError) -> GraphicsError {
+    match e {
+        SwapchainCreationError::OomError(_) => GraphicsError::OutOfMemory,
+        SwapchainCreationError::DeviceLost => GraphicsError::DeviceLost,
+        SwapchainCreationError::SurfaceLost => GraphicsError::SurfaceLost,
+        e => GraphicsError::vulkan(e),
+    }
 }
 
 fn create_image_views(
     images: &Vec<Arc<SwapchainImage>>,
     swapchain: Arc<Swapchain>,
-) -> Vec<Arc<ImageView<SwapchainImage>>> {
+) -> Result<Vec<Arc<ImageView<SwapchainImage>>>, GraphicsError> {
     images
         .iter()
         .map(|image| {
@@ -717,7 +1391,7 @@ fn create_image_views(
                     ..Default::default()
                 },
             )
-            .unwrap()
+            .map_err(GraphicsError::vulkan)
         })
         .collect()
 }
@@ -726,7 +1400,8 @@ fn create_main_render_pass(
     device: Arc<Device>,
     swapchain_format: Format,
     depth_format: Format,
-) -> Arc<RenderPass> {
+    final_layout: ImageLayout,
+) -> Result<Arc<RenderPass>, GraphicsError> {
     let attachments = vec![
         AttachmentDescription {
             format: Some(swapchain_format),
@@ -736,7 +1411,7 @@ fn create_main_render_pass(
             stencil_load_op: LoadOp::DontCare,
             stencil_store_op: StoreOp::DontCare,
             initial_layout: ImageLayout::Undefined,
-            final_layout: ImageLayout::PresentSrc,
+            final_layout: final_layout,
             ..Default::default()
         },
         AttachmentDescription {
@@ -789,14 +1464,17 @@ fn create_main_render_pass(
         }],
         ..Default::default()
     };
-    RenderPass::new(device.clone(), create_info).expect("Failed to create render pass!")
+    RenderPass::new(device.clone(), create_info).map_err(GraphicsError::vulkan)
 }
 
-fn create_framebuffers(
-    image_views: &Vec<Arc<ImageView<SwapchainImage>>>,
+fn create_framebuffers<I>(
+    image_views: &Vec<Arc<ImageView<I>>>,
     render_pass: Arc<RenderPass>,
     depth_buffers: &Vec<Arc<ImageView<AttachmentImage>>>,
-) -> Vec<Arc<Framebuffer>> {
+) -> Result<Vec<Arc<Framebuffer>>, GraphicsError>
+where
+    I: ImageAccess + std::fmt::Debug + 'static,
+{
     image_views
         .iter()
         .zip(depth_buffers)
@@ -807,7 +1485,7 @@ fn create_framebuffers(
                 layers: 1,
                 ..Default::default()
             };
-            Framebuffer::new(render_pass.clone(), create_info).unwrap()
+            Framebuffer::new(render_pass.clone(), create_info).map_err(GraphicsError::vulkan)
         })
         .collect()
 }
@@ -830,11 +1508,14 @@ fn select_image_format(
     None
 }
 
+type DepthBuffers = Vec<Arc<ImageView<AttachmentImage>>>;
+
 fn create_depth_buffer(
     device: Arc<Device>,
-    swapchain: Arc<Swapchain>,
+    extent: [u32; 2],
+    count: usize,
     allocator: &StandardMemoryAllocator,
-) -> (Vec<Arc<ImageView<AttachmentImage>>>, Format) {
+) -> Result<(DepthBuffers, Format), GraphicsError> {
     let format_candidates = [
         Format::D16_UNORM,
         Format::D32_SFLOAT,
@@ -849,19 +1530,19 @@ fn create_depth_buffer(
         FormatFeatures::DEPTH_STENCIL_ATTACHMENT,
         &format_candidates,
     )
-    .unwrap();
+    .ok_or(GraphicsError::UnsupportedFormat(Format::D16_UNORM))?;
 
-    let mut views = Vec::new();
-    views.resize_with(swapchain.image_count() as usize, || {
+    let mut views = Vec::with_capacity(count);
+    for _ in 0..count {
         let image = AttachmentImage::with_usage(
             allocator,
-            swapchain.image_extent(),
+            extent,
             format,
             ImageUsage::DEPTH_STENCIL_ATTACHMENT,
         )
-        .unwrap();
+        .map_err(GraphicsError::vulkan)?;
 
-        ImageView::new(
+        let view = ImageView::new(
             image.clone(),
             ImageViewCreateInfo {
                 format: Some(format),
@@ -869,8 +1550,9 @@ fn create_depth_buffer(
                 ..ImageViewCreateInfo::from_image(&image)
             },
         )
-        .unwrap()
-    });
+        .map_err(GraphicsError::vulkan)?;
+        views.push(view);
+    }
 
-    (views, format)
+    Ok((views, format))
 }
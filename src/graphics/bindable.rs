@@ -1,40 +1,94 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
 use vulkano::{
     command_buffer::{
         allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder,
-        PrimaryAutoCommandBuffer,
+        SecondaryAutoCommandBuffer,
     },
     pipeline::PipelineLayout,
     shader::ShaderModule,
 };
 
-use super::{pipeline::PipelineBuilder, Graphics};
+use super::{bounds::Aabb, pipeline::PipelineBuilder};
 
 mod buffer;
+mod dynamic;
 mod god_bindable;
+mod instance;
 mod push_constant;
 mod shader;
 mod texture;
 mod uniform;
 
 pub use buffer::*;
+pub use dynamic::*;
 pub use god_bindable::*;
+pub use instance::*;
 pub use push_constant::*;
 pub use shader::*;
 pub use texture::*;
 pub use uniform::*;
 
-pub trait Bindable {
+/// Set when recorded command buffers no longer match what should be drawn.
+/// Owned by `Graphics`, bindables that bake their data into command buffers keep a clone.
+#[derive(Clone, Default)]
+pub struct DirtyFlag(Arc<AtomicBool>);
+
+impl DirtyFlag {
+    pub fn mark(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    /// Returns whether the flag was set and clears it.
+    pub fn take(&self) -> bool {
+        self.0.swap(false, Ordering::AcqRel)
+    }
+}
+
+/// For bindables whose gpu side data has to be refreshed every frame instead of when they are
+/// bound, since binding only happens when a command buffer is recorded.
+pub trait FrameUpdate {
+    fn update(&self, in_flight_index: usize);
+    /// Early updates run before all others, for updates that write into other bindables,
+    /// e.g. a scene graph writing transforms into uniforms.
+    fn runs_early(&self) -> bool {
+        false
+    }
+}
+
+/// Draws are recorded into secondary command buffers, possibly on several threads at once.
+pub type CommandBuilder =
+    AutoCommandBufferBuilder<SecondaryAutoCommandBuffer, StandardCommandBufferAllocator>;
+
+/// The parts of `Graphics` that bindables need while a command buffer is recorded.
+#[derive(Clone, Copy)]
+pub struct BindContext {
+    pub in_flight_index: usize,
+}
+
+pub trait Bindable: Send + Sync {
     fn bind_to_pipeline(&self, builder: &mut PipelineBuilder, index_count: &mut u32);
     fn bind(
         &self,
-        _gfx: &Graphics,
-        _builder: &mut AutoCommandBufferBuilder<
-            PrimaryAutoCommandBuffer,
-            StandardCommandBufferAllocator,
-        >,
+        _ctx: &BindContext,
+        _builder: &mut CommandBuilder,
         _pipeline_layout: Arc<PipelineLayout>,
     ) {
     }
+    /// Number of instances to draw, for bindables that provide per instance data.
+    fn instance_count(&self) -> Option<u32> {
+        None
+    }
+    /// Number of indices to draw, for index buffers whose length can change. Takes precedence
+    /// over the count set in `bind_to_pipeline`, which only runs for the first drawable.
+    fn index_count(&self) -> Option<u32> {
+        None
+    }
+    /// Bounding box of the vertices in model space, for culling.
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
 }
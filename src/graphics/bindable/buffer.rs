@@ -1,24 +1,30 @@
-use std::{mem::align_of, sync::Arc};
+use std::{
+    mem::{align_of, size_of},
+    sync::Arc,
+};
 
 use vulkano::{
     buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
-    command_buffer::{
-        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
-        CopyBufferInfoTyped, PrimaryAutoCommandBuffer, PrimaryCommandBufferAbstract,
-    },
-    memory::allocator::{AllocationCreateInfo, DeviceLayout},
+    command_buffer::CopyBufferInfoTyped,
+    memory::allocator::{AllocationCreateInfo, DeviceLayout, MemoryUsage},
     pipeline::{graphics::vertex_input::Vertex, PipelineLayout},
-    sync::GpuFuture,
 };
 
-use crate::graphics::{pipeline::PipelineBuilder, Graphics};
+use crate::graphics::{
+    bounds::{Aabb, PositionedVertex},
+    error::GraphicsError,
+    pipeline::PipelineBuilder,
+    upload::UploadTarget,
+    Graphics,
+};
 
-use super::Bindable;
+use super::{BindContext, Bindable, CommandBuilder};
 pub struct VertexBuffer<T>
 where
     T: Vertex + BufferContents,
 {
     subbuffer: Subbuffer<[T]>,
+    bounds: Option<Aabb>,
 }
 
 impl<T> Bindable for VertexBuffer<T>
@@ -29,168 +35,129 @@ where
         builder.vertex_buffer_description = Some(T::per_vertex());
     }
 
-    fn bind(
-        &self,
-        _gfx: &Graphics,
-        builder: &mut AutoCommandBufferBuilder<
-            PrimaryAutoCommandBuffer,
-            StandardCommandBufferAllocator,
-        >,
-        _: Arc<PipelineLayout>,
-    ) {
+    fn bind(&self, _ctx: &BindContext, builder: &mut CommandBuilder, _: Arc<PipelineLayout>) {
         builder.bind_vertex_buffers(0, self.subbuffer.clone());
     }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
 }
 
 impl<T> VertexBuffer<T>
 where
     T: Vertex + BufferContents,
 {
-    pub fn new(gfx: &Graphics, vertices: Vec<T>) -> Arc<Self>
+    /// The buffer can be bound right away, the data is uploaded before the next frame renders.
+    pub fn new(gfx: &Graphics, vertices: Vec<T>) -> Result<Arc<Self>, GraphicsError>
     where
         T: Vertex + BufferContents,
     {
-        let staging_buffer = Buffer::from_iter(
-            gfx.get_allocator(),
-            BufferCreateInfo {
-                usage: BufferUsage::TRANSFER_SRC,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                usage: vulkano::memory::allocator::MemoryUsage::Upload,
-                ..Default::default()
-            },
-            vertices.into_iter(),
-        )
-        .expect("Failed to create vertex buffer.");
-
-        let main_buffer = Buffer::new(
-            gfx.get_allocator(),
-            BufferCreateInfo {
-                usage: BufferUsage::TRANSFER_DST | BufferUsage::VERTEX_BUFFER,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                usage: vulkano::memory::allocator::MemoryUsage::DeviceOnly,
-                ..Default::default()
-            },
-            DeviceLayout::from_size_alignment(staging_buffer.size(), align_of::<T>() as u64)
-                .unwrap(),
-        )
-        .expect("Failed to create index buffer.");
-
-        let main_subbuffer = Subbuffer::new(main_buffer).cast_aligned();
-
-        let mut builder = AutoCommandBufferBuilder::primary(
-            gfx.get_cmd_allocator(),
-            gfx.graphics_queue().queue_family_index(),
-            CommandBufferUsage::OneTimeSubmit,
-        )
-        .unwrap();
-
-        builder
-            .copy_buffer(CopyBufferInfoTyped::buffers(
-                staging_buffer,
-                main_subbuffer.clone(),
-            ))
-            .unwrap();
-
-        let fence = builder
-            .build()
-            .unwrap()
-            .execute(gfx.graphics_queue())
-            .unwrap()
-            .then_signal_fence_and_flush()
-            .unwrap();
-
-        fence.wait(None).unwrap();
-
-        Arc::new(Self {
-            subbuffer: main_subbuffer,
-        })
+        Self::with_bounds(gfx, vertices, None)
+    }
+
+    /// Like `new`, but keeps the bounding box of the vertex positions so drawables using the
+    /// buffer can be culled.
+    pub fn new_bounded(gfx: &Graphics, vertices: Vec<T>) -> Result<Arc<Self>, GraphicsError>
+    where
+        T: PositionedVertex,
+    {
+        let bounds = Aabb::from_points(vertices.iter().map(|p| p.position()));
+        Self::with_bounds(gfx, vertices, bounds)
+    }
+
+    /// For vertices whose bounds are already known.
+    pub fn with_bounds(
+        gfx: &Graphics,
+        vertices: Vec<T>,
+        bounds: Option<Aabb>,
+    ) -> Result<Arc<Self>, GraphicsError> {
+        Ok(Arc::new(Self {
+            subbuffer: upload_to_device(gfx, vertices, BufferUsage::VERTEX_BUFFER)?,
+            bounds: bounds,
+        }))
     }
 }
 
 pub struct IndexBuffer {
     subbuffer: Subbuffer<[u32]>,
+    index_count: u32,
 }
 
 impl Bindable for IndexBuffer {
     fn bind_to_pipeline(&self, _builder: &mut PipelineBuilder, index_count: &mut u32) {
-        *index_count = self.subbuffer.len().try_into().unwrap();
+        *index_count = self.index_count;
     }
-    fn bind(
-        &self,
-        _gfx: &Graphics,
-        builder: &mut AutoCommandBufferBuilder<
-            PrimaryAutoCommandBuffer,
-            StandardCommandBufferAllocator,
-        >,
-        _: Arc<PipelineLayout>,
-    ) {
+    fn bind(&self, _ctx: &BindContext, builder: &mut CommandBuilder, _: Arc<PipelineLayout>) {
         builder.bind_index_buffer(self.subbuffer.clone());
     }
 }
 
 impl IndexBuffer {
-    pub fn new(gfx: &Graphics, indices: Vec<u32>) -> Arc<Self> {
-        let staging_buffer = Buffer::from_iter(
-            gfx.get_allocator(),
-            BufferCreateInfo {
-                usage: BufferUsage::TRANSFER_SRC,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                usage: vulkano::memory::allocator::MemoryUsage::Upload,
-                ..Default::default()
-            },
-            indices.into_iter(),
-        )
-        .expect("Failed to create index buffer.");
-
-        let main_buffer = Buffer::new(
-            gfx.get_allocator(),
-            BufferCreateInfo {
-                usage: BufferUsage::TRANSFER_DST | BufferUsage::INDEX_BUFFER,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                usage: vulkano::memory::allocator::MemoryUsage::DeviceOnly,
-                ..Default::default()
-            },
-            DeviceLayout::from_size_alignment(staging_buffer.size(), align_of::<u32>() as u64)
-                .unwrap(),
-        )
-        .expect("Failed to create index buffer.");
-
-        let main_subbuffer = Subbuffer::new(main_buffer).cast_aligned();
-
-        let mut builder = AutoCommandBufferBuilder::primary(
-            gfx.get_cmd_allocator(),
-            gfx.graphics_queue().queue_family_index(),
-            CommandBufferUsage::OneTimeSubmit,
-        )
-        .unwrap();
-
-        builder
-            .copy_buffer(CopyBufferInfoTyped::buffers(
-                staging_buffer,
-                main_subbuffer.clone(),
-            ))
-            .unwrap();
-
-        let fence = builder
-            .build()
-            .unwrap()
-            .execute(gfx.graphics_queue())
-            .unwrap()
-            .then_signal_fence_and_flush()
-            .unwrap();
-
-        fence.wait(None).unwrap();
-
-        Arc::new(Self {
-            subbuffer: main_subbuffer,
-        })
+    /// The buffer can be bound right away, the data is uploaded before the next frame renders.
+    pub fn new(gfx: &Graphics, indices: Vec<u32>) -> Result<Arc<Self>, GraphicsError> {
+        let index_count = u32::try_from(indices.len())
+            .map_err(|_| GraphicsError::vulkan("more indices than a draw can use"))?;
+        Ok(Arc::new(Self {
+            subbuffer: upload_to_device(gfx, indices, BufferUsage::INDEX_BUFFER)?,
+            index_count: index_count,
+        }))
     }
 }
+
+/// Creates a device local buffer and records a copy of `data` into it in the upload batch.
+fn upload_to_device<T>(
+    gfx: &Graphics,
+    data: Vec<T>,
+    usage: BufferUsage,
+) -> Result<Subbuffer<[T]>, GraphicsError>
+where
+    T: BufferContents,
+{
+    let layout = DeviceLayout::from_size_alignment(
+        (data.len() * size_of::<T>()) as u64,
+        align_of::<T>() as u64,
+    )
+    .ok_or(GraphicsError::EmptyUpload)?;
+
+    let staging_buffer = Buffer::from_iter(
+        gfx.get_allocator(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            usage: MemoryUsage::Upload,
+            ..Default::default()
+        },
+        data.into_iter(),
+    )?;
+
+    let main_buffer = Buffer::new(
+        gfx.get_allocator(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_DST | usage,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            usage: MemoryUsage::DeviceOnly,
+            ..Default::default()
+        },
+        layout,
+    )?;
+
+    let main_subbuffer: Subbuffer<[T]> = Subbuffer::new(main_buffer).cast_aligned();
+
+    let destination = main_subbuffer.clone();
+    gfx.record_upload(
+        UploadTarget::Buffer(destination.buffer().clone()),
+        |builder| {
+            builder
+                .copy_buffer(CopyBufferInfoTyped::buffers(staging_buffer, destination))
+                .map_err(GraphicsError::vulkan)?;
+            Ok(())
+        },
+    )?;
+
+    Ok(main_subbuffer)
+}
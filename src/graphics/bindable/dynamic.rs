@@ -0,0 +1,394 @@
+use std::{
+    ops::Range,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferError, BufferUsage, Subbuffer},
+    memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator},
+    pipeline::{graphics::vertex_input::Vertex, PipelineLayout},
+};
+
+use crate::graphics::{error::GraphicsError, pipeline::PipelineBuilder, Graphics};
+
+use super::{BindContext, Bindable, CommandBuilder, DirtyFlag, FrameUpdate};
+
+const MIN_CAPACITY: usize = 16;
+
+struct DynamicBufferState<T> {
+    data: Vec<T>,
+    /// One host visible buffer per in flight frame, so updating one never waits on the gpu.
+    subbuffers: Vec<Subbuffer<[T]>>,
+    /// The part of each subbuffer that is out of date, `None` if it's valid.
+    stale_ranges: Vec<Option<Range<usize>>>,
+}
+
+/// The part the dynamic buffers and the instance buffer share, copies the cpu side data into the
+/// subbuffer of the frame that is about to be recorded.
+pub(super) struct DynamicBuffer<T>
+where
+    T: BufferContents + Clone,
+{
+    allocator: Arc<StandardMemoryAllocator>,
+    usage: BufferUsage,
+    state: Mutex<DynamicBufferState<T>>,
+    /// Marked when the length changes or a buffer is reallocated.
+    dirty: DirtyFlag,
+}
+
+impl<T> DynamicBuffer<T>
+where
+    T: BufferContents + Clone,
+{
+    pub(super) fn new(
+        gfx: &Graphics,
+        data: Vec<T>,
+        usage: BufferUsage,
+    ) -> Result<Self, GraphicsError> {
+        let allocator = gfx.get_shared_allocator();
+        let capacity = data.len().next_power_of_two().max(MIN_CAPACITY);
+
+        let subbuffers = (0..gfx.get_in_flight_count())
+            .map(|_| create_host_subbuffer(&allocator, usage, capacity))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            allocator: allocator,
+            usage: usage,
+            state: Mutex::new(DynamicBufferState {
+                stale_ranges: vec![Some(0..data.len()); gfx.get_in_flight_count()],
+                data: data,
+                subbuffers: subbuffers,
+            }),
+            dirty: gfx.get_dirty_flag(),
+        })
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.lock_state().data.len()
+    }
+
+    /// A panic while the lock is held can at worst leave stale data behind, which the stale
+    /// ranges already cover, so a poisoned lock is logged and used anyway.
+    fn lock_state(&self) -> MutexGuard<'_, DynamicBufferState<T>> {
+        self.state.lock().unwrap_or_else(|e| {
+            log::error!("Dynamic buffer mutex was poisoned, continuing with its data");
+            e.into_inner()
+        })
+    }
+
+    pub(super) fn access_data<R>(&self, accessing_function: impl FnOnce(&mut Vec<T>) -> R) -> R {
+        let mut state = self.lock_state();
+        let previous_len = state.data.len();
+
+        let result = accessing_function(&mut state.data);
+
+        let len = state.data.len();
+        mark_stale(&mut state.stale_ranges, 0..len);
+        // the length is part of the recorded draw call
+        if len != previous_len {
+            self.dirty.mark();
+        }
+        result
+    }
+
+    pub(super) fn write(&self, offset: usize, values: &[T]) {
+        let mut state = self.lock_state();
+        let previous_len = state.data.len();
+
+        let written = overwrite_or_extend(&mut state.data, offset, values);
+
+        mark_stale(&mut state.stale_ranges, written.clone());
+        if written.end > previous_len {
+            self.dirty.mark();
+        }
+    }
+
+    pub(super) fn update_range(&self, range: Range<usize>, update: impl FnOnce(&mut [T])) {
+        let mut state = self.lock_state();
+        update(&mut state.data[range.clone()]);
+        mark_stale(&mut state.stale_ranges, range);
+    }
+
+    pub(super) fn subbuffer(&self, in_flight_index: usize) -> Subbuffer<[T]> {
+        self.lock_state().subbuffers[in_flight_index].clone()
+    }
+
+    pub(super) fn update(&self, in_flight_index: usize) {
+        let mut state = self.lock_state();
+
+        let Some(stale_range) = state.stale_ranges[in_flight_index].clone() else {
+            return;
+        };
+
+        let len = state.data.len();
+        let mut range = clamp_range(stale_range, len);
+        if (state.subbuffers[in_flight_index].len() as usize) < len {
+            match create_host_subbuffer(&self.allocator, self.usage, len.next_power_of_two()) {
+                Ok(subbuffer) => {
+                    state.subbuffers[in_flight_index] = subbuffer;
+                    // the new buffer has none of the old contents
+                    range = 0..len;
+                    state.stale_ranges[in_flight_index] = Some(range.clone());
+                    // command buffers still bind the old buffer
+                    self.dirty.mark();
+                }
+                Err(e) => {
+                    log::error!("Failed to grow dynamic buffer: {e}");
+                    return;
+                }
+            }
+        }
+
+        let state = &mut *state;
+        // the subbuffer may still be in use, in which case it's retried next frame
+        let Ok(mut buffer) = state.subbuffers[in_flight_index].write() else {
+            return;
+        };
+        buffer[range.clone()].clone_from_slice(&state.data[range]);
+        state.stale_ranges[in_flight_index] = None;
+    }
+}
+
+/// Overwrites the elements starting at `offset` and appends the ones past the end, returns the
+/// range that was written. Panics if `offset` is past the end.
+fn overwrite_or_extend<T: Clone>(data: &mut Vec<T>, offset: usize, values: &[T]) -> Range<usize> {
+    let len = data.len();
+    assert!(
+        offset <= len,
+        "write at {offset} leaves a gap after the last element at {len}"
+    );
+
+    let end = offset + values.len();
+    let overlap = end.min(len);
+    data[offset..overlap].clone_from_slice(&values[..overlap - offset]);
+    data.extend_from_slice(&values[overlap - offset..]);
+    offset..end
+}
+
+/// The data may have shrunk since `range` was marked stale.
+fn clamp_range(range: Range<usize>, len: usize) -> Range<usize> {
+    range.start.min(len)..range.end.min(len)
+}
+
+/// Extends the stale range of every subbuffer to include `range`.
+fn mark_stale(stale_ranges: &mut [Option<Range<usize>>], range: Range<usize>) {
+    for stale_range in stale_ranges {
+        *stale_range = match stale_range.take() {
+            Some(p) => Some(p.start.min(range.start)..p.end.max(range.end)),
+            None => Some(range.clone()),
+        };
+    }
+}
+
+/// A vertex buffer whose contents can change every frame, bound to the first vertex buffer
+/// binding. Unlike `VertexBuffer` it has no bounds, so drawables using it are never culled.
+pub struct DynamicVertexBuffer<T>
+where
+    T: Vertex + BufferContents + Clone,
+{
+    buffer: DynamicBuffer<T>,
+}
+
+impl<T> DynamicVertexBuffer<T>
+where
+    T: Vertex + BufferContents + Clone,
+{
+    /// Changes made through any of the methods reach the gpu before the next frame renders.
+    pub fn new(gfx: &Graphics, vertices: Vec<T>) -> Result<Arc<Self>, GraphicsError> {
+        let vertex_buffer = Arc::new(Self {
+            buffer: DynamicBuffer::new(gfx, vertices, BufferUsage::VERTEX_BUFFER)?,
+        });
+
+        gfx.register_frame_update(Arc::downgrade(&vertex_buffer) as _);
+
+        Ok(vertex_buffer)
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copies all vertices again, prefer `write` or `update_range` for small changes.
+    pub fn access_vertices<R>(&self, accessing_function: impl FnOnce(&mut Vec<T>) -> R) -> R {
+        self.buffer.access_data(accessing_function)
+    }
+
+    /// Overwrites the vertices starting at `offset`, growing the buffer if they go past the end.
+    /// Panics if `offset` is past the end.
+    pub fn write(&self, offset: usize, vertices: &[T]) {
+        self.buffer.write(offset, vertices)
+    }
+
+    /// Only `range` is copied to the gpu. Panics if `range` is out of bounds.
+    pub fn update_range(&self, range: Range<usize>, update: impl FnOnce(&mut [T])) {
+        self.buffer.update_range(range, update)
+    }
+}
+
+impl<T> FrameUpdate for DynamicVertexBuffer<T>
+where
+    T: Vertex + BufferContents + Clone,
+{
+    fn update(&self, in_flight_index: usize) {
+        self.buffer.update(in_flight_index);
+    }
+}
+
+impl<T> Bindable for DynamicVertexBuffer<T>
+where
+    T: Vertex + BufferContents + Clone,
+{
+    fn bind_to_pipeline(&self, builder: &mut PipelineBuilder, _index_count: &mut u32) {
+        builder.vertex_buffer_description = Some(T::per_vertex());
+    }
+    fn bind(&self, ctx: &BindContext, builder: &mut CommandBuilder, _: Arc<PipelineLayout>) {
+        builder.bind_vertex_buffers(0, self.buffer.subbuffer(ctx.in_flight_index));
+    }
+}
+
+/// An index buffer whose contents can change every frame. Drawables using it draw as many
+/// indices as it currently holds.
+pub struct DynamicIndexBuffer {
+    buffer: DynamicBuffer<u32>,
+}
+
+impl DynamicIndexBuffer {
+    /// Changes made through any of the methods reach the gpu before the next frame renders.
+    pub fn new(gfx: &Graphics, indices: Vec<u32>) -> Result<Arc<Self>, GraphicsError> {
+        let index_buffer = Arc::new(Self {
+            buffer: DynamicBuffer::new(gfx, indices, BufferUsage::INDEX_BUFFER)?,
+        });
+
+        gfx.register_frame_update(Arc::downgrade(&index_buffer) as _);
+
+        Ok(index_buffer)
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copies all indices again, prefer `write` or `update_range` for small changes.
+    pub fn access_indices<R>(&self, accessing_function: impl FnOnce(&mut Vec<u32>) -> R) -> R {
+        self.buffer.access_data(accessing_function)
+    }
+
+    /// Overwrites the indices starting at `offset`, growing the buffer if they go past the end.
+    /// Panics if `offset` is past the end.
+    pub fn write(&self, offset: usize, indices: &[u32]) {
+        self.buffer.write(offset, indices)
+    }
+
+    /// Only `range` is copied to the gpu. Panics if `range` is out of bounds.
+    pub fn update_range(&self, range: Range<usize>, update: impl FnOnce(&mut [u32])) {
+        self.buffer.update_range(range, update)
+    }
+}
+
+impl FrameUpdate for DynamicIndexBuffer {
+    fn update(&self, in_flight_index: usize) {
+        self.buffer.update(in_flight_index);
+    }
+}
+
+impl Bindable for DynamicIndexBuffer {
+    fn bind_to_pipeline(&self, _builder: &mut PipelineBuilder, index_count: &mut u32) {
+        *index_count = self.len() as u32;
+    }
+    fn bind(&self, ctx: &BindContext, builder: &mut CommandBuilder, _: Arc<PipelineLayout>) {
+        builder.bind_index_buffer(self.buffer.subbuffer(ctx.in_flight_index));
+    }
+    fn index_count(&self) -> Option<u32> {
+        Some(self.len() as u32)
+    }
+}
+
+/// A host visible buffer that can be written to without a transfer, used by the bindables that
+/// change their contents at runtime.
+pub(super) fn create_host_subbuffer<T>(
+    allocator: &StandardMemoryAllocator,
+    usage: BufferUsage,
+    capacity: usize,
+) -> Result<Subbuffer<[T]>, BufferError>
+where
+    T: BufferContents,
+{
+    Buffer::new_slice::<T>(
+        allocator,
+        BufferCreateInfo {
+            usage: usage,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            usage: MemoryUsage::Upload,
+            ..Default::default()
+        },
+        capacity as u64,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mark_stale_starts_from_valid() {
+        let mut stale_ranges = vec![None, None];
+        mark_stale(&mut stale_ranges, 2..5);
+        assert_eq!(stale_ranges, vec![Some(2..5), Some(2..5)]);
+    }
+
+    #[test]
+    fn mark_stale_unions_ranges() {
+        let mut stale_ranges = vec![Some(4..6), None, Some(0..1)];
+        mark_stale(&mut stale_ranges, 2..5);
+        assert_eq!(stale_ranges, vec![Some(2..6), Some(2..5), Some(0..5)]);
+    }
+
+    #[test]
+    fn write_inside_overwrites() {
+        let mut data = vec![0, 1, 2, 3];
+        let written = overwrite_or_extend(&mut data, 1, &[10, 11]);
+        assert_eq!(data, vec![0, 10, 11, 3]);
+        assert_eq!(written, 1..3);
+    }
+
+    #[test]
+    fn write_past_end_overwrites_and_extends() {
+        let mut data = vec![0, 1, 2];
+        let written = overwrite_or_extend(&mut data, 2, &[10, 11, 12]);
+        assert_eq!(data, vec![0, 1, 10, 11, 12]);
+        assert_eq!(written, 2..5);
+    }
+
+    #[test]
+    fn write_at_end_appends() {
+        let mut data = vec![0, 1];
+        let written = overwrite_or_extend(&mut data, 2, &[2]);
+        assert_eq!(data, vec![0, 1, 2]);
+        assert_eq!(written, 2..3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn write_with_gap_panics() {
+        let mut data = vec![0, 1];
+        overwrite_or_extend(&mut data, 3, &[3]);
+    }
+
+    #[test]
+    fn clamp_range_to_shrunk_data() {
+        assert_eq!(clamp_range(2..8, 5), 2..5);
+        assert_eq!(clamp_range(6..8, 5), 5..5);
+        assert_eq!(clamp_range(1..3, 5), 1..3);
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{BufferContents, BufferUsage},
+    pipeline::{graphics::vertex_input::Vertex, PipelineLayout},
+};
+
+use crate::graphics::{error::GraphicsError, pipeline::PipelineBuilder, Graphics};
+
+use super::{dynamic::DynamicBuffer, BindContext, Bindable, CommandBuilder, FrameUpdate};
+
+/// Per instance vertex data, bound to the second vertex buffer binding.
+/// Instances can be added, removed and changed at any time, the drawable using this is drawn
+/// once per instance.
+pub struct InstanceBuffer<T>
+where
+    T: Vertex + BufferContents + Clone,
+{
+    buffer: DynamicBuffer<T>,
+}
+
+impl<T> InstanceBuffer<T>
+where
+    T: Vertex + BufferContents + Clone,
+{
+    pub fn new(gfx: &Graphics, instances: Vec<T>) -> Result<Arc<Self>, GraphicsError> {
+        let instance_buffer = Arc::new(Self {
+            buffer: DynamicBuffer::new(gfx, instances, BufferUsage::VERTEX_BUFFER)?,
+        });
+
+        gfx.register_frame_update(Arc::downgrade(&instance_buffer) as _);
+
+        Ok(instance_buffer)
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the index of the new instance.
+    pub fn push(&self, instance: T) -> usize {
+        self.access_instances(|instances| {
+            instances.push(instance);
+            instances.len() - 1
+        })
+    }
+
+    /// Later instances move down by one index. Panics if `index` is out of bounds.
+    pub fn remove(&self, index: usize) -> T {
+        self.access_instances(|instances| instances.remove(index))
+    }
+
+    /// Does nothing if there's no instance at `index`.
+    pub fn update(&self, index: usize, update: impl FnOnce(&mut T)) {
+        self.access_instances(|instances| {
+            if let Some(instance) = instances.get_mut(index) {
+                update(instance);
+            }
+        })
+    }
+
+    pub fn access_instances<R>(&self, accessing_function: impl FnOnce(&mut Vec<T>) -> R) -> R {
+        self.buffer.access_data(accessing_function)
+    }
+}
+
+impl<T> FrameUpdate for InstanceBuffer<T>
+where
+    T: Vertex + BufferContents + Clone,
+{
+    fn update(&self, in_flight_index: usize) {
+        self.buffer.update(in_flight_index);
+    }
+}
+
+impl<T> Bindable for InstanceBuffer<T>
+where
+    T: Vertex + BufferContents + Clone,
+{
+    fn bind_to_pipeline(&self, builder: &mut PipelineBuilder, _index_count: &mut u32) {
+        builder.instance_buffer_description = Some(T::per_instance());
+    }
+    fn bind(&self, ctx: &BindContext, builder: &mut CommandBuilder, _: Arc<PipelineLayout>) {
+        builder.bind_vertex_buffers(1, self.buffer.subbuffer(ctx.in_flight_index));
+    }
+    fn instance_count(&self) -> Option<u32> {
+        Some(self.len() as u32)
+    }
+}
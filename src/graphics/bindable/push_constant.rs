@@ -5,17 +5,13 @@ use std::{
 
 use vulkano::{
     buffer::BufferContents,
-    command_buffer::{
-        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder,
-        PrimaryAutoCommandBuffer,
-    },
     pipeline::{layout::PushConstantRange, PipelineLayout},
     shader::ShaderStages,
 };
 
 use crate::graphics::{pipeline::PipelineBuilder, Graphics};
 
-use super::Bindable;
+use super::{BindContext, Bindable, CommandBuilder, DirtyFlag};
 
 pub struct PushConstant<T>
 where
@@ -23,13 +19,14 @@ where
 {
     push_constant_range: PushConstantRange,
     data: Mutex<T>,
+    dirty: DirtyFlag,
 }
 
 impl<T> PushConstant<T>
 where
-    T: BufferContents + Clone,
+    T: BufferContents + Clone + PartialEq,
 {
-    pub fn new(_gfx: &Graphics, offset: u32, data: T, stages: ShaderStages) -> Arc<Self> {
+    pub fn new(gfx: &Graphics, offset: u32, data: T, stages: ShaderStages) -> Arc<Self> {
         let range = PushConstantRange {
             stages: stages,
             offset: offset,
@@ -39,20 +36,29 @@ where
         Arc::new(Self {
             push_constant_range: range,
             data: Mutex::new(data),
+            dirty: gfx.get_dirty_flag(),
         })
     }
 
+    /// Push constants are baked into the command buffers, so changing them causes them to be
+    /// re-recorded. Writing the same value again doesn't.
     pub fn access_data(&self, accessing_function: impl FnOnce(&mut T)) {
         match self.data.lock() {
-            Ok(mut guard) => accessing_function(&mut *guard),
-            Err(_e) => println!("Push Constant access failed!"),
+            Ok(mut guard) => {
+                let previous = guard.clone();
+                accessing_function(&mut *guard);
+                if *guard != previous {
+                    self.dirty.mark();
+                }
+            }
+            Err(e) => log::error!("Push constant mutex could not be locked! {e}"),
         }
     }
 }
 
 impl<T> Bindable for PushConstant<T>
 where
-    T: BufferContents + Clone,
+    T: BufferContents + Clone + PartialEq,
 {
     fn bind_to_pipeline(&self, builder: &mut PipelineBuilder, _index_count: &mut u32) {
         builder
@@ -61,17 +67,19 @@ where
     }
     fn bind(
         &self,
-        _gfx: &Graphics,
-        builder: &mut AutoCommandBufferBuilder<
-            PrimaryAutoCommandBuffer,
-            StandardCommandBufferAllocator,
-        >,
+        _ctx: &BindContext,
+        builder: &mut CommandBuilder,
         pipeline_layout: Arc<PipelineLayout>,
     ) {
-        builder.push_constants(
-            pipeline_layout.clone(),
-            self.push_constant_range.offset,
-            self.data.lock().unwrap().clone(),
-        );
+        match self.data.lock() {
+            Ok(data) => {
+                builder.push_constants(
+                    pipeline_layout.clone(),
+                    self.push_constant_range.offset,
+                    data.clone(),
+                );
+            }
+            Err(e) => log::error!("Push constant mutex could not be locked! {e}"),
+        }
     }
 }
@@ -1,7 +1,8 @@
-use std::{io::Cursor, sync::Arc};
+use std::sync::Arc;
 
 use vulkano::{
-    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryCommandBufferAbstract},
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::CopyBufferToImageInfo,
     descriptor_set::{
         layout::{
             DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
@@ -10,15 +11,22 @@ use vulkano::{
         PersistentDescriptorSet, WriteDescriptorSet,
     },
     format::Format,
-    image::{view::ImageView, ImageDimensions, ImmutableImage},
+    image::{
+        view::ImageView, ImageAccess, ImageCreateFlags, ImageDimensions, ImageLayout, ImageUsage,
+        ImmutableImage, MipmapsCount,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryUsage},
+    pipeline::PipelineLayout,
     sampler::{Sampler, SamplerCreateInfo},
     shader::ShaderStages,
-    sync::GpuFuture,
 };
 
-use crate::graphics::{pipeline::PipelineBuilder, Graphics};
+use crate::graphics::{
+    error::GraphicsError, import::ImageData, pipeline::PipelineBuilder, upload::UploadTarget,
+    Graphics,
+};
 
-use super::Bindable;
+use super::{BindContext, Bindable, CommandBuilder};
 
 pub struct Texture {
     pub image: Arc<ImageView<ImmutableImage>>,
@@ -35,12 +43,9 @@ impl Bindable for Texture {
 
     fn bind(
         &self,
-        _gfx: &Graphics,
-        builder: &mut AutoCommandBufferBuilder<
-            vulkano::command_buffer::PrimaryAutoCommandBuffer,
-            vulkano::command_buffer::allocator::StandardCommandBufferAllocator,
-        >,
-        pipeline_layout: Arc<vulkano::pipeline::PipelineLayout>,
+        _ctx: &BindContext,
+        builder: &mut CommandBuilder,
+        pipeline_layout: Arc<PipelineLayout>,
     ) {
         builder.bind_descriptor_sets(
             vulkano::pipeline::PipelineBindPoint::Graphics,
@@ -52,50 +57,97 @@ impl Bindable for Texture {
 }
 
 impl Texture {
-    pub fn new(gfx: &Graphics, path: &str, set_num: u32, binding: u32) -> Arc<Self> {
-        let mut uploads = AutoCommandBufferBuilder::primary(
-            gfx.get_cmd_allocator(),
-            gfx.graphics_queue().queue_family_index(),
-            CommandBufferUsage::OneTimeSubmit,
+    /// Loads a png, see `ImageData::load_png`.
+    pub fn new(
+        gfx: &Graphics,
+        path: &str,
+        set_num: u32,
+        binding: u32,
+    ) -> Result<Arc<Self>, GraphicsError> {
+        let image = ImageData::load_png(path)?;
+        Self::from_rgba(
+            gfx,
+            image.width,
+            image.height,
+            image.pixels,
+            set_num,
+            binding,
         )
-        .unwrap();
+    }
+
+    /// A single white pixel, for materials without a texture.
+    pub fn white(gfx: &Graphics, set_num: u32, binding: u32) -> Result<Arc<Self>, GraphicsError> {
+        Self::from_rgba(gfx, 1, 1, vec![255; 4], set_num, binding)
+    }
+
+    /// `pixels` are 8 bit srgb rgba, row by row.
+    pub fn from_rgba(
+        gfx: &Graphics,
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+        set_num: u32,
+        binding: u32,
+    ) -> Result<Arc<Self>, GraphicsError> {
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(GraphicsError::PixelDataMismatch {
+                expected: expected,
+                actual: pixels.len(),
+            });
+        }
 
         let image = {
-            let bytes = std::fs::read(path).expect("Texture file not found.");
-            let cursor = Cursor::new(bytes);
-            let decoder = png::Decoder::new(cursor);
-            let mut reader = decoder.read_info().unwrap();
-            let info = reader.info();
             let dimensions = ImageDimensions::Dim2d {
-                width: info.width,
-                height: info.height,
+                width: width,
+                height: height,
                 array_layers: 1,
             };
-            let mut image_data = vec![0; (info.width * info.height * 4) as usize];
-            reader.next_frame(&mut image_data).unwrap();
 
-            let image = ImmutableImage::from_iter(
+            let staging_buffer = Buffer::from_iter(
+                gfx.get_allocator(),
+                BufferCreateInfo {
+                    usage: BufferUsage::TRANSFER_SRC,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    usage: MemoryUsage::Upload,
+                    ..Default::default()
+                },
+                pixels,
+            )?;
+
+            let (image, initializer) = ImmutableImage::uninitialized(
                 gfx.get_allocator(),
-                image_data,
                 dimensions,
-                vulkano::image::MipmapsCount::One,
                 Format::R8G8B8A8_SRGB,
-                &mut uploads,
+                MipmapsCount::One,
+                ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                ImageCreateFlags::empty(),
+                ImageLayout::ShaderReadOnlyOptimal,
+                [],
             )
-            .unwrap();
-            ImageView::new_default(image).unwrap()
-        };
+            .map_err(GraphicsError::vulkan)?;
 
-        let fence = uploads
-            .build()
-            .unwrap()
-            .execute(gfx.graphics_queue())
-            .unwrap()
-            .then_signal_fence_and_flush()
-            .unwrap();
+            let target = UploadTarget::Image(
+                image.inner().image.clone(),
+                ImageLayout::ShaderReadOnlyOptimal,
+            );
+            gfx.record_upload(target, |builder| {
+                builder
+                    .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
+                        staging_buffer,
+                        initializer,
+                    ))
+                    .map_err(GraphicsError::vulkan)?;
+                Ok(())
+            })?;
 
-        let sampler =
-            Sampler::new(gfx.get_device(), SamplerCreateInfo::simple_repeat_linear()).unwrap();
+            ImageView::new_default(image).map_err(GraphicsError::vulkan)?
+        };
+
+        let sampler = Sampler::new(gfx.get_device(), SamplerCreateInfo::simple_repeat_linear())
+            .map_err(GraphicsError::vulkan)?;
 
         let layout = DescriptorSetLayout::new(
             gfx.get_device(),
@@ -116,23 +168,21 @@ impl Texture {
                 ..Default::default()
             },
         )
-        .unwrap();
-
-        fence.wait(None).unwrap();
+        .map_err(GraphicsError::vulkan)?;
 
         let set = PersistentDescriptorSet::new(
             gfx.get_descriptor_set_allocator(),
             layout.clone(),
             [WriteDescriptorSet::image_view(binding, image.clone())],
         )
-        .unwrap();
+        .map_err(GraphicsError::vulkan)?;
 
-        Arc::new(Self {
+        Ok(Arc::new(Self {
             image: image,
             sampler: sampler,
             layout: layout,
             descriptor_set: set,
             set_num: set_num,
-        })
+        }))
     }
 }
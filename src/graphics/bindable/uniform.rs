@@ -6,10 +6,6 @@ use std::{
 
 use vulkano::{
     buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
-    command_buffer::{
-        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder,
-        PrimaryAutoCommandBuffer,
-    },
     descriptor_set::{
         layout::{
             DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
@@ -23,9 +19,9 @@ use vulkano::{
     sync::Sharing,
 };
 
-use crate::graphics::{pipeline::PipelineBuilder, Graphics};
+use crate::graphics::{error::GraphicsError, pipeline::PipelineBuilder, Graphics};
 
-use super::Bindable;
+use super::{BindContext, Bindable, CommandBuilder, FrameUpdate};
 
 struct UniformBufferMutablePart<T> {
     pub subbuffer_validity: Vec<bool>,
@@ -47,9 +43,15 @@ impl<T> UniformBuffer<T>
 where
     T: BufferContents + Clone,
 {
-    pub fn new(gfx: &Graphics, binding: u32, data: T, stages: ShaderStages) -> Arc<Self> {
-        let subbuffers: Vec<Subbuffer<T>> = (0..gfx.get_in_flight_count())
-            .into_iter()
+    /// Registers the buffer for per frame updates, the data written with `access_data` reaches
+    /// the gpu before the next frame that uses it is submitted.
+    pub fn new(
+        gfx: &Graphics,
+        binding: u32,
+        data: T,
+        stages: ShaderStages,
+    ) -> Result<Arc<Self>, GraphicsError> {
+        let subbuffers = (0..gfx.get_in_flight_count())
             .map(|_| {
                 Buffer::new_sized::<T>(
                     gfx.get_allocator(),
@@ -63,13 +65,12 @@ where
                         ..Default::default()
                     },
                 )
-                .unwrap()
             })
-            .collect();
+            .collect::<Result<Vec<Subbuffer<T>>, _>>()?;
 
         subbuffers.iter().for_each(|p| match p.write() {
             Ok(mut guard) => *guard = data.clone(),
-            Err(e) => println!("error when writing initial value to uniform buffer: {e}"),
+            Err(e) => log::error!("error when writing initial value to uniform buffer: {e}"),
         });
 
         let layout = DescriptorSetLayout::new(
@@ -87,12 +88,12 @@ where
                 ..Default::default()
             },
         )
-        .unwrap();
+        .map_err(GraphicsError::vulkan)?;
 
         let mut sets = Vec::with_capacity(gfx.get_in_flight_count());
 
-        for set in subbuffers.iter().map(|subbuffer| {
-            PersistentDescriptorSet::new(
+        for subbuffer in subbuffers.iter() {
+            let set = PersistentDescriptorSet::new(
                 gfx.get_descriptor_set_allocator(),
                 layout.clone(),
                 [WriteDescriptorSet::buffer_with_range(
@@ -101,12 +102,11 @@ where
                     0..size_of::<T>() as u64,
                 )],
             )
-            .unwrap()
-        }) {
+            .map_err(GraphicsError::vulkan)?;
             sets.push(set);
         }
 
-        Arc::new(Self {
+        let uniform = Arc::new(Self {
             subbuffers: subbuffers,
             layout: layout,
             descriptor_sets: sets,
@@ -115,7 +115,11 @@ where
                 subbuffer_validity: vec![true; gfx.get_in_flight_count()],
                 staging_buffer: data,
             }),
-        })
+        });
+
+        gfx.register_frame_update(Arc::downgrade(&uniform) as _);
+
+        Ok(uniform)
     }
 
     pub fn access_data(&self, accessing_function: impl FnOnce(&mut T)) {
@@ -129,7 +133,7 @@ where
                 accessing_function(&mut mutex_guard.staging_buffer);
             }
             Err(e) => {
-                println!("Uniform buffer mutex could not be locked! {e}");
+                log::error!("Uniform buffer mutex could not be locked! {e}");
             }
         }
     }
@@ -144,19 +148,31 @@ where
     }
     fn bind(
         &self,
-        gfx: &Graphics,
-        builder: &mut AutoCommandBufferBuilder<
-            PrimaryAutoCommandBuffer,
-            StandardCommandBufferAllocator,
-        >,
+        ctx: &BindContext,
+        builder: &mut CommandBuilder,
         pipeline_layout: Arc<PipelineLayout>,
     ) {
-        let in_flight_index = gfx.get_in_flight_index();
+        let in_flight_index = ctx.in_flight_index;
 
+        builder.bind_descriptor_sets(
+            vulkano::pipeline::PipelineBindPoint::Graphics,
+            pipeline_layout.clone(),
+            0,
+            self.descriptor_sets[in_flight_index].clone(),
+        );
+    }
+}
+
+impl<T> FrameUpdate for UniformBuffer<T>
+where
+    T: BufferContents + Clone,
+{
+    fn update(&self, in_flight_index: usize) {
         match self.mutable_part.lock() {
             Ok(mut mutex_guard) => {
                 let valid = mutex_guard.subbuffer_validity[in_flight_index];
                 if !valid {
+                    // the subbuffer may still be in use, in which case it's retried next frame
                     if let Ok(mut buffer) = self.subbuffers[in_flight_index].write() {
                         *buffer = mutex_guard.staging_buffer.clone();
                         mutex_guard.subbuffer_validity[in_flight_index] = true;
@@ -164,15 +180,8 @@ where
                 }
             }
             Err(e) => {
-                println!("Uniform buffer mutex could not be locked! {e}");
+                log::error!("Uniform buffer mutex could not be locked! {e}");
             }
         }
-
-        builder.bind_descriptor_sets(
-            vulkano::pipeline::PipelineBindPoint::Graphics,
-            pipeline_layout.clone(),
-            0,
-            self.descriptor_sets[in_flight_index].clone(),
-        );
     }
 }
@@ -0,0 +1,186 @@
+use cgmath::{InnerSpace, Matrix, Matrix4, Point3, Transform, Vector4};
+
+/// For vertex types whose position can be read back on the cpu, see `VertexBuffer::new_bounded`.
+pub trait PositionedVertex {
+    fn position(&self) -> [f32; 3];
+}
+
+/// Axis aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    /// `None` if there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = Point3::from(points.next()?);
+
+        Some(points.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |bounds, point| Self {
+                min: Point3::new(
+                    bounds.min.x.min(point[0]),
+                    bounds.min.y.min(point[1]),
+                    bounds.min.z.min(point[2]),
+                ),
+                max: Point3::new(
+                    bounds.max.x.max(point[0]),
+                    bounds.max.y.max(point[1]),
+                    bounds.max.z.max(point[2]),
+                ),
+            },
+        ))
+    }
+
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// The box around the transformed corners, so it may be larger than necessary.
+    pub fn transformed(&self, transform: &Matrix4<f32>) -> Self {
+        Self::from_points(
+            self.corners()
+                .map(|corner| transform.transform_point(corner).into()),
+        )
+        .unwrap()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::from_points([
+            self.min.into(),
+            self.max.into(),
+            other.min.into(),
+            other.max.into(),
+        ])
+        .unwrap()
+    }
+}
+
+/// The six planes of a view projection's clip volume, pointing inwards.
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Works for both perspective and orthographic projections. The near plane is taken as
+    /// `z = -w`, which also covers the `0..w` depth range.
+    pub fn from_view_projection(view_projection: &Matrix4<f32>) -> Self {
+        let rows = [0, 1, 2, 3].map(|i| view_projection.row(i));
+
+        let planes = [
+            rows[3] + rows[0],
+            rows[3] - rows[0],
+            rows[3] + rows[1],
+            rows[3] - rows[1],
+            rows[3] + rows[2],
+            rows[3] - rows[2],
+        ]
+        .map(|plane| plane / plane.truncate().magnitude().max(f32::EPSILON));
+
+        Self { planes: planes }
+    }
+
+    /// Conservative, boxes near the frustum's edges may pass without being visible.
+    pub fn intersects(&self, bounds: &Aabb) -> bool {
+        let pick = |normal: f32, min: f32, max: f32| if normal >= 0.0 { max } else { min };
+
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the plane normal
+            let corner = Vector4::new(
+                pick(plane.x, bounds.min.x, bounds.max.x),
+                pick(plane.y, bounds.min.y, bounds.max.y),
+                pick(plane.z, bounds.min.z, bounds.max.z),
+                1.0,
+            );
+            plane.dot(corner) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{perspective, Deg, Vector3};
+
+    fn unit_box_at(x: f32, y: f32, z: f32) -> Aabb {
+        Aabb {
+            min: Point3::new(x - 0.5, y - 0.5, z - 0.5),
+            max: Point3::new(x + 0.5, y + 0.5, z + 0.5),
+        }
+    }
+
+    /// Looks down -z from the origin, near at 1 and far at 10.
+    fn frustum() -> Frustum {
+        Frustum::from_view_projection(&perspective(Deg(90.0), 1.0, 1.0, 10.0))
+    }
+
+    #[test]
+    fn transformed_translates_and_scales() {
+        let transform =
+            Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0)) * Matrix4::from_scale(2.0);
+        let bounds = unit_box_at(0.0, 0.0, 0.0).transformed(&transform);
+
+        assert_eq!(bounds.min, Point3::new(0.0, 1.0, 2.0));
+        assert_eq!(bounds.max, Point3::new(2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn transformed_encloses_rotated_corners() {
+        let bounds = unit_box_at(0.0, 0.0, 0.0).transformed(&Matrix4::from_angle_y(Deg(45.0)));
+        let half_diagonal = 0.5 * 2.0f32.sqrt();
+
+        assert!((bounds.max.x - half_diagonal).abs() < 1e-5);
+        assert!((bounds.min.z + half_diagonal).abs() < 1e-5);
+        assert!((bounds.max.y - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn box_inside_intersects() {
+        assert!(frustum().intersects(&unit_box_at(0.0, 0.0, -5.0)));
+    }
+
+    #[test]
+    fn boxes_outside_are_rejected() {
+        let frustum = frustum();
+        // behind the camera, and beside it past the 90 degree fov
+        assert!(!frustum.intersects(&unit_box_at(0.0, 0.0, 5.0)));
+        assert!(!frustum.intersects(&unit_box_at(8.0, 0.0, -5.0)));
+        assert!(!frustum.intersects(&unit_box_at(0.0, -8.0, -5.0)));
+    }
+
+    #[test]
+    fn box_straddling_a_side_plane_intersects() {
+        assert!(frustum().intersects(&unit_box_at(5.0, 0.0, -5.0)));
+    }
+
+    #[test]
+    fn near_plane_is_respected() {
+        let frustum = frustum();
+        assert!(!frustum.intersects(&unit_box_at(0.0, 0.0, -0.25)));
+        assert!(frustum.intersects(&unit_box_at(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn far_plane_is_respected() {
+        let frustum = frustum();
+        assert!(!frustum.intersects(&unit_box_at(0.0, 0.0, -10.75)));
+        assert!(frustum.intersects(&unit_box_at(0.0, 0.0, -10.0)));
+    }
+}
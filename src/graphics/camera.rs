@@ -0,0 +1,254 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bytemuck::Zeroable;
+use cgmath::{Deg, Matrix4, Point3, SquareMatrix, Vector3};
+use vulkano::shader::ShaderStages;
+
+use super::{
+    bindable::{DirtyFlag, UniformBuffer},
+    drawable::Drawable,
+    error::GraphicsError,
+    Graphics,
+};
+
+/// Layout of the camera uniform, `set = 0, binding = 0` in the shaders that use a camera.
+#[derive(Clone, Copy, Zeroable, bytemuck::Pod)]
+#[repr(C)]
+pub struct CameraUbo {
+    pub view_projection: [[f32; 4]; 4],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective {
+        fov_y: Deg<f32>,
+    },
+    /// `height` world units are visible vertically, the width follows the aspect ratio.
+    Orthographic {
+        height: f32,
+    },
+    /// One unit per pixel with the origin in the middle of the target and y pointing up.
+    /// Position, target and up are ignored.
+    Screen,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraSettings {
+    pub projection: Projection,
+    pub position: Point3<f32>,
+    pub target: Point3<f32>,
+    pub up: Vector3<f32>,
+    pub near: f32,
+    pub far: f32,
+    /// Narrows the field of view or the visible area, 2.0 shows things twice as big.
+    pub zoom: f32,
+}
+
+impl CameraSettings {
+    pub fn perspective(fov_y: Deg<f32>, position: Point3<f32>, target: Point3<f32>) -> Self {
+        Self {
+            projection: Projection::Perspective { fov_y: fov_y },
+            position: position,
+            target: target,
+            ..Default::default()
+        }
+    }
+
+    pub fn orthographic(height: f32, position: Point3<f32>, target: Point3<f32>) -> Self {
+        Self {
+            projection: Projection::Orthographic { height: height },
+            position: position,
+            target: target,
+            ..Default::default()
+        }
+    }
+
+    /// For 2D drawables positioned in pixels.
+    pub fn screen() -> Self {
+        Self {
+            projection: Projection::Screen,
+            near: -10.0,
+            far: 10.0,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            projection: Projection::Perspective { fov_y: Deg(60.0) },
+            position: Point3::new(0.0, 0.8, 1.5),
+            target: Point3::new(0.0, 0.0, 0.0),
+            // vulkan's y axis points down
+            up: Vector3::new(0.0, -1.0, 0.0),
+            near: 0.1,
+            far: 10.0,
+            zoom: 1.0,
+        }
+    }
+}
+
+struct CameraState {
+    settings: CameraSettings,
+    extent: [u32; 2],
+}
+
+/// Owns a view projection uniform that any number of drawables can bind as a shared bindable.
+/// Cameras are resized along with the swapchain.
+pub struct Camera {
+    uniform: Arc<UniformBuffer<CameraUbo>>,
+    state: Mutex<CameraState>,
+    /// Culling and depth sorting depend on the camera, so moving it re-records the frame.
+    dirty: DirtyFlag,
+}
+
+impl Camera {
+    pub fn new(gfx: &Graphics, settings: CameraSettings) -> Result<Arc<Self>, GraphicsError> {
+        let extent = gfx.get_extent();
+
+        let uniform = UniformBuffer::new(
+            gfx,
+            0,
+            CameraUbo {
+                view_projection: view_projection(&settings, extent).into(),
+            },
+            ShaderStages::VERTEX,
+        )?;
+
+        let camera = Arc::new(Self {
+            uniform: uniform,
+            state: Mutex::new(CameraState {
+                settings: settings,
+                extent: extent,
+            }),
+            dirty: gfx.get_dirty_flag(),
+        });
+
+        gfx.register_camera(Arc::downgrade(&camera));
+
+        Ok(camera)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CameraState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether `drawable` renders with this camera, that is binds its uniform.
+    pub fn is_bound_by(&self, drawable: &impl Drawable) -> bool {
+        let uniform = Arc::as_ptr(&self.uniform) as *const ();
+        drawable
+            .get_shared_bindables()
+            .iter()
+            .chain(drawable.get_bindables())
+            .any(|bindable| Arc::as_ptr(bindable) as *const () == uniform)
+    }
+
+    /// Bind this as a shared bindable, it's a uniform buffer at binding 0.
+    pub fn get_uniform(&self) -> Arc<UniformBuffer<CameraUbo>> {
+        self.uniform.clone()
+    }
+
+    pub fn get_settings(&self) -> CameraSettings {
+        self.lock().settings
+    }
+
+    pub fn set_settings(&self, settings: CameraSettings) {
+        self.update(|current| *current = settings);
+    }
+
+    /// The uniform is written on the next frame. Nothing happens if the settings didn't change.
+    pub fn update(&self, update: impl FnOnce(&mut CameraSettings)) {
+        let mut state = self.lock();
+        let previous = state.settings;
+        update(&mut state.settings);
+        if state.settings != previous {
+            self.write_uniform(&state);
+            self.dirty.mark();
+        }
+    }
+
+    pub fn look_at(&self, position: Point3<f32>, target: Point3<f32>) {
+        self.update(|settings| {
+            settings.position = position;
+            settings.target = target;
+        });
+    }
+
+    pub fn set_zoom(&self, zoom: f32) {
+        self.update(|settings| settings.zoom = zoom);
+    }
+
+    pub fn get_view(&self) -> Matrix4<f32> {
+        view(&self.lock().settings)
+    }
+
+    pub fn get_projection(&self) -> Matrix4<f32> {
+        let state = self.lock();
+        projection(&state.settings, state.extent)
+    }
+
+    pub fn get_view_projection(&self) -> Matrix4<f32> {
+        let state = self.lock();
+        view_projection(&state.settings, state.extent)
+    }
+
+    /// Called by `Graphics::recreate_swapchain`.
+    pub(super) fn resize(&self, extent: [u32; 2]) {
+        let mut state = self.lock();
+        state.extent = extent;
+        self.write_uniform(&state);
+    }
+
+    fn write_uniform(&self, state: &CameraState) {
+        let matrix = view_projection(&state.settings, state.extent);
+        self.uniform
+            .access_data(|data| data.view_projection = matrix.into());
+    }
+}
+
+fn view(settings: &CameraSettings) -> Matrix4<f32> {
+    match settings.projection {
+        Projection::Screen => Matrix4::identity(),
+        _ => Matrix4::look_at_rh(settings.position, settings.target, settings.up),
+    }
+}
+
+fn projection(settings: &CameraSettings, extent: [u32; 2]) -> Matrix4<f32> {
+    let [width, height] = extent.map(|p| p.max(1) as f32);
+    let aspect = width / height;
+
+    match settings.projection {
+        Projection::Perspective { fov_y } => {
+            cgmath::perspective(fov_y / settings.zoom, aspect, settings.near, settings.far)
+        }
+        Projection::Orthographic { height } => {
+            let half_height = height / 2.0 / settings.zoom;
+            let half_width = half_height * aspect;
+            cgmath::ortho(
+                -half_width,
+                half_width,
+                -half_height,
+                half_height,
+                settings.near,
+                settings.far,
+            )
+        }
+        Projection::Screen => {
+            let half_width = width / 2.0 / settings.zoom;
+            let half_height = height / 2.0 / settings.zoom;
+            cgmath::ortho(
+                -half_width,
+                half_width,
+                half_height,
+                -half_height,
+                settings.near,
+                settings.far,
+            )
+        }
+    }
+}
+
+fn view_projection(settings: &CameraSettings, extent: [u32; 2]) -> Matrix4<f32> {
+    projection(settings, extent) * view(settings)
+}
@@ -0,0 +1,130 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
+
+use vulkano::format::Format;
+
+use super::error::GraphicsError;
+
+/// A frame read back from the gpu, always stored as 8 bit RGBA.
+pub struct CapturedFrame {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl CapturedFrame {
+    /// Whether `from_raw` can convert texels in `format`.
+    pub fn supports_format(format: Format) -> bool {
+        matches!(
+            format,
+            Format::R8G8B8A8_UNORM
+                | Format::R8G8B8A8_SRGB
+                | Format::B8G8R8A8_UNORM
+                | Format::B8G8R8A8_SRGB
+        )
+    }
+
+    /// Converts raw texel data in `format` to RGBA.
+    /// Only 8 bit per channel RGBA and BGRA formats are supported.
+    pub fn from_raw(
+        width: u32,
+        height: u32,
+        format: Format,
+        mut data: Vec<u8>,
+    ) -> Result<Self, GraphicsError> {
+        if !Self::supports_format(format) {
+            return Err(GraphicsError::UnsupportedFormat(format));
+        }
+        if matches!(format, Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB) {
+            data.chunks_exact_mut(4).for_each(|texel| texel.swap(0, 2));
+        }
+
+        Ok(Self {
+            width: width,
+            height: height,
+            data: data,
+        })
+    }
+
+    /// Returns the RGBA value of the pixel at (x, y), with (0, 0) being the top left corner.
+    /// `None` outside of the frame.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let offset = (y as usize * self.width as usize + x as usize) * 4;
+        self.data.get(offset..offset + 4)?.try_into().ok()
+    }
+
+    /// Loads an 8 bit RGB or RGBA png, RGB images get an opaque alpha channel.
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self, png::DecodingError> {
+        let file = File::open(path)?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        buffer.truncate(info.buffer_size());
+
+        let data = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            other => {
+                return Err(png::DecodingError::IoError(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsupported png color type {other:?}"),
+                )))
+            }
+        };
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            data: data,
+        })
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), png::EncodingError> {
+        let file = File::create(path)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bgra_is_converted_to_rgba() {
+        let frame =
+            CapturedFrame::from_raw(1, 1, Format::B8G8R8A8_UNORM, vec![1, 2, 3, 4]).unwrap();
+        assert_eq!(frame.pixel(0, 0), Some([3, 2, 1, 4]));
+    }
+
+    #[test]
+    fn pixels_outside_the_frame_are_none() {
+        let frame = CapturedFrame::from_raw(2, 1, Format::R8G8B8A8_SRGB, vec![0; 8]).unwrap();
+        assert_eq!(frame.pixel(1, 0), Some([0; 4]));
+        assert_eq!(frame.pixel(2, 0), None);
+        assert_eq!(frame.pixel(0, 1), None);
+    }
+
+    #[test]
+    fn unsupported_formats_are_rejected() {
+        let result = CapturedFrame::from_raw(1, 1, Format::R16G16B16A16_SFLOAT, vec![0; 8]);
+        assert!(matches!(result, Err(GraphicsError::UnsupportedFormat(_))));
+    }
+}
@@ -0,0 +1,163 @@
+use std::{fmt, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::device::DeviceSelector;
+
+/// Settings for `Graphics` that can be changed without recompiling.
+/// Missing fields in a config file fall back to their default value.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsConfig {
+    pub window_size: [u32; 2],
+    pub window_title: String,
+    pub application_name: String,
+    pub in_flight_count: usize,
+    /// Threads used to record draw commands, 0 uses every available core.
+    pub recording_threads: usize,
+    /// If true MAILBOX will always be used if available.
+    /// If false FIFO will be preferred.
+    pub prefer_mailbox_present_mode: bool,
+    /// Can be overridden with the `BATAKO_VALIDATION` environment variable.
+    pub validation: bool,
+    /// Layers that aren't installed are skipped.
+    pub validation_layers: Vec<String>,
+    /// Least severe debug messenger message that gets logged.
+    pub debug_severity: DebugSeverity,
+    pub clear_color: [f32; 4],
+    /// Forces a physical device, can be overridden with the `BATAKO_DEVICE` environment variable.
+    pub device: Option<DeviceSelector>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DebugSeverity {
+    Error,
+    Warning,
+    Info,
+    Verbose,
+}
+
+impl Default for GraphicsConfig {
+    fn default() -> Self {
+        Self {
+            window_size: [600, 400],
+            window_title: String::from("Batako"),
+            application_name: String::from("Rosten"),
+            in_flight_count: 2,
+            recording_threads: 0,
+            prefer_mailbox_present_mode: false,
+            validation: false,
+            validation_layers: vec![String::from("VK_LAYER_KHRONOS_validation")],
+            debug_severity: DebugSeverity::Warning,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            device: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    /// The config parsed, but a value is out of range, names the offending field.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "could not read config: {e}"),
+            ConfigError::Parse(e) => write!(f, "could not parse config: {e}"),
+            ConfigError::Invalid(field) => write!(f, "invalid config value for {field}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl GraphicsConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> Result<Self, ConfigError> {
+        let config: Self = ron::from_str(text).map_err(ConfigError::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the values that parse but can't be used, for configs that weren't loaded.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        // recording_threads needs no check, 0 means every core
+        if self.in_flight_count == 0 {
+            return Err(ConfigError::Invalid(
+                "in_flight_count, at least one frame is needed",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether validation layers should be requested, `BATAKO_VALIDATION=1` or `=0` takes
+    /// precedence over the config value.
+    pub fn validation_enabled(&self) -> bool {
+        match std::env::var("BATAKO_VALIDATION").as_deref() {
+            Ok("1") | Ok("true") => true,
+            Ok("0") | Ok("false") => false,
+            _ => self.validation,
+        }
+    }
+
+    /// The device forced by `BATAKO_DEVICE`, which is either an index or part of a device name,
+    /// or by the config value.
+    pub fn device_selector(&self) -> Option<DeviceSelector> {
+        match std::env::var("BATAKO_DEVICE") {
+            Ok(text) if !text.trim().is_empty() => Some(DeviceSelector::parse(&text)),
+            _ => self.device.clone(),
+        }
+    }
+
+    /// Loads the config at `path`, falling back to the defaults if it doesn't exist or is invalid.
+    pub fn load_or_default(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match Self::load(path) {
+            Ok(config) => config,
+            Err(ConfigError::Io(e)) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                log::warn!(
+                    "Using default graphics config, {} is invalid: {e}",
+                    path.display()
+                );
+                Self::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let config = GraphicsConfig::parse("(in_flight_count: 3)").unwrap();
+        assert_eq!(config.in_flight_count, 3);
+        assert_eq!(config.recording_threads, 0);
+    }
+
+    #[test]
+    fn zero_in_flight_frames_are_rejected() {
+        let result = GraphicsConfig::parse("(in_flight_count: 0)");
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn constructed_configs_are_validated_too() {
+        let config = GraphicsConfig {
+            in_flight_count: 0,
+            ..Default::default()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+        assert!(GraphicsConfig::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,270 @@
+use std::{fmt, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use vulkano::{
+    device::physical::{PhysicalDevice, PhysicalDeviceType},
+    format::{Format, FormatFeatures},
+    swapchain::Surface,
+    Version,
+};
+
+use super::error::GraphicsError;
+
+/// Formats whose support is listed in a `DeviceInfo`.
+const REPORTED_FORMATS: [Format; 10] = [
+    Format::R8G8B8A8_UNORM,
+    Format::R8G8B8A8_SRGB,
+    Format::B8G8R8A8_UNORM,
+    Format::B8G8R8A8_SRGB,
+    Format::R16G16B16A16_SFLOAT,
+    Format::D16_UNORM,
+    Format::D32_SFLOAT,
+    Format::D16_UNORM_S8_UINT,
+    Format::D24_UNORM_S8_UINT,
+    Format::D32_SFLOAT_S8_UINT,
+];
+
+/// Forces a specific physical device instead of picking the best ranked one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceSelector {
+    /// Position in the instance's device enumeration order.
+    Index(usize),
+    /// Case insensitive substring of the device name, e.g. "llvmpipe" for lavapipe.
+    Name(String),
+}
+
+impl DeviceSelector {
+    /// Numbers are read as an index, anything else as a name.
+    pub fn parse(text: &str) -> Self {
+        match text.trim().parse::<usize>() {
+            Ok(index) => DeviceSelector::Index(index),
+            Err(_) => DeviceSelector::Name(String::from(text.trim())),
+        }
+    }
+
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        match self {
+            DeviceSelector::Index(index) => info.index == *index,
+            DeviceSelector::Name(name) => info
+                .name
+                .to_lowercase()
+                .contains(name.to_lowercase().as_str()),
+        }
+    }
+}
+
+impl fmt::Display for DeviceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceSelector::Index(index) => write!(f, "device #{index}"),
+            DeviceSelector::Name(name) => write!(f, "device \"{name}\""),
+        }
+    }
+}
+
+/// A subset of the device limits that is useful when debugging.
+#[derive(Clone, Debug)]
+pub struct DeviceLimits {
+    pub max_image_dimension_2d: u32,
+    pub max_framebuffer_width: u32,
+    pub max_framebuffer_height: u32,
+    pub max_push_constants_size: u32,
+    pub max_uniform_buffer_range: u32,
+    pub max_bound_descriptor_sets: u32,
+    pub max_vertex_input_bindings: u32,
+    pub max_color_attachments: u32,
+    pub max_sampler_anisotropy: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct FormatSupport {
+    pub format: Format,
+    /// Features with optimal tiling.
+    pub features: FormatFeatures,
+}
+
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub index: usize,
+    pub name: String,
+    pub device_type: PhysicalDeviceType,
+    pub api_version: Version,
+    pub driver_version: u32,
+    pub driver_name: Option<String>,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// Whether the device meets the renderer's requirements.
+    pub suitable: bool,
+    pub limits: DeviceLimits,
+    pub formats: Vec<FormatSupport>,
+}
+
+impl DeviceInfo {
+    pub fn new(index: usize, physical_device: &Arc<PhysicalDevice>, suitable: bool) -> Self {
+        let properties = physical_device.properties();
+
+        let formats = REPORTED_FORMATS
+            .iter()
+            .filter_map(|format| {
+                let features = physical_device
+                    .format_properties(*format)
+                    .ok()?
+                    .optimal_tiling_features;
+                (!features.is_empty()).then_some(FormatSupport {
+                    format: *format,
+                    features: features,
+                })
+            })
+            .collect();
+
+        Self {
+            index: index,
+            name: properties.device_name.clone(),
+            device_type: properties.device_type,
+            api_version: physical_device.api_version(),
+            driver_version: properties.driver_version,
+            driver_name: properties.driver_name.clone(),
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            suitable: suitable,
+            limits: DeviceLimits {
+                max_image_dimension_2d: properties.max_image_dimension2_d,
+                max_framebuffer_width: properties.max_framebuffer_width,
+                max_framebuffer_height: properties.max_framebuffer_height,
+                max_push_constants_size: properties.max_push_constants_size,
+                max_uniform_buffer_range: properties.max_uniform_buffer_range,
+                max_bound_descriptor_sets: properties.max_bound_descriptor_sets,
+                max_vertex_input_bindings: properties.max_vertex_input_bindings,
+                max_color_attachments: properties.max_color_attachments,
+                max_sampler_anisotropy: properties.max_sampler_anisotropy,
+            },
+            formats: formats,
+        }
+    }
+
+    /// Lower is better, used when no device is explicitly selected.
+    fn rank(&self) -> u32 {
+        match self.device_type {
+            PhysicalDeviceType::DiscreteGpu => 0,
+            PhysicalDeviceType::IntegratedGpu => 1,
+            PhysicalDeviceType::VirtualGpu => 2,
+            PhysicalDeviceType::Cpu => 3,
+            PhysicalDeviceType::Other => 4,
+            _ => 5,
+        }
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "#{} {} ({:?}){}",
+            self.index,
+            self.name,
+            self.device_type,
+            if self.suitable { "" } else { " [unsuitable]" }
+        )?;
+        writeln!(
+            f,
+            "    api {}, driver {} {:#x}, vendor {:#06x}, device {:#06x}",
+            self.api_version,
+            self.driver_name.as_deref().unwrap_or("unknown"),
+            self.driver_version,
+            self.vendor_id,
+            self.device_id
+        )?;
+        writeln!(f, "    limits: {:?}", self.limits)?;
+        for support in &self.formats {
+            writeln!(f, "    {:?}: {:?}", support.format, support.features)?;
+        }
+        Ok(())
+    }
+}
+
+/// Summary of the device a `Graphics` is running on, meant to be pasted into bug reports.
+#[derive(Clone, Debug)]
+pub struct DeviceReport {
+    pub device: DeviceInfo,
+    pub instance_api_version: Version,
+    pub validation_enabled: bool,
+    pub graphics_queue_family: u32,
+    pub present_queue_family: Option<u32>,
+    pub transfer_queue_family: u32,
+    pub target_format: Format,
+    pub extent: [u32; 2],
+    pub headless: bool,
+}
+
+impl fmt::Display for DeviceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "instance api {}, validation {}",
+            self.instance_api_version,
+            if self.validation_enabled { "on" } else { "off" }
+        )?;
+        write!(f, "{}", self.device)?;
+        writeln!(
+            f,
+            "queue families: graphics {}, present {:?}, transfer {}",
+            self.graphics_queue_family, self.present_queue_family, self.transfer_queue_family
+        )?;
+        writeln!(
+            f,
+            "target: {}x{} {:?}{}",
+            self.extent[0],
+            self.extent[1],
+            self.target_format,
+            if self.headless { " (headless)" } else { "" }
+        )
+    }
+}
+
+/// Picks the device matching `selector`, or the best ranked suitable device if there is none.
+/// A selected device that doesn't exist or isn't suitable is an error rather than a fallback.
+pub(super) fn select_physical_device(
+    devices: Vec<Arc<PhysicalDevice>>,
+    surface: Option<&Arc<Surface>>,
+    selector: Option<&DeviceSelector>,
+    is_suitable: impl Fn(Arc<PhysicalDevice>, Option<&Arc<Surface>>) -> bool,
+) -> Result<Arc<PhysicalDevice>, GraphicsError> {
+    let candidates: Vec<(DeviceInfo, Arc<PhysicalDevice>)> = devices
+        .into_iter()
+        .enumerate()
+        .map(|(index, device)| {
+            let suitable = is_suitable(device.clone(), surface);
+            (DeviceInfo::new(index, &device, suitable), device)
+        })
+        .collect();
+
+    let selected = match selector {
+        Some(selector) => {
+            let found = candidates.iter().find(|(info, _)| selector.matches(info));
+            match found {
+                Some((info, device)) if info.suitable => Some((info, device)),
+                _ => {
+                    log::error!("Requested {selector} is not available or not suitable.");
+                    candidates
+                        .iter()
+                        .for_each(|(info, _)| log::info!("Available: {info}"));
+                    return Err(GraphicsError::RequestedDeviceUnavailable(
+                        selector.to_string(),
+                    ));
+                }
+            }
+        }
+        None => candidates
+            .iter()
+            .filter(|(info, _)| info.suitable)
+            .min_by_key(|(info, _)| info.rank())
+            .map(|(info, device)| (info, device)),
+    };
+
+    let (info, device) = selected.ok_or(GraphicsError::NoSuitableDevice)?;
+
+    log::info!("Using device: {} (type: {:?})", info.name, info.device_type);
+    log::debug!("{info}");
+
+    Ok(device.clone())
+}
@@ -1,32 +1,148 @@
-use std::sync::{Arc, Weak};
+use std::any::TypeId;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, Weak};
+use vulkano::device::Device;
 use vulkano::pipeline::{GraphicsPipeline, PipelineLayout};
 
 use super::bindable::Bindable;
+use super::bounds::Aabb;
+use super::error::GraphicsError;
 use super::pipeline::PipelineBuilder;
+use super::registry::{DrawableHandle, Registration};
 
 pub trait Drawable {
     fn get_bindables(&self) -> &Vec<Arc<dyn Bindable>>;
     fn get_shared_bindables(&self) -> &Vec<Arc<dyn Bindable>>;
     fn get_pipeline(&self) -> Arc<GraphicsPipeline>;
     fn get_index_count(&self) -> u32;
+    fn get_instance_count(&self) -> u32;
     fn get_pipeline_layout(&self) -> Arc<PipelineLayout>;
 }
 
+/// Layers are drawn in declaration order, see `DrawableRegistry::set_layer`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderLayer {
+    /// Sorted front to back to save fragment work.
+    #[default]
+    Opaque,
+    /// Alpha blended and sorted back to front, doesn't write depth.
+    Transparent,
+    /// Alpha blended overlays without depth testing, ordered only by their order value.
+    Ui,
+}
+
 pub struct DrawableSharedPart {
     pub bindables: Vec<Arc<dyn Bindable>>,
+    /// The pipeline for the opaque layer.
     pub pipeline: Arc<GraphicsPipeline>,
     pub layout: Arc<PipelineLayout>,
     pub index_count: u32,
+    device: Arc<Device>,
+    /// Kept around to build the pipelines of other layers once they're needed.
+    pipeline_builder: PipelineBuilder,
+    layer_pipelines: Mutex<HashMap<RenderLayer, Arc<GraphicsPipeline>>>,
+}
+
+impl DrawableSharedPart {
+    /// Builds the pipeline for `layer` on first use. All of them share the same layout.
+    pub fn get_layer_pipeline(
+        &self,
+        layer: RenderLayer,
+    ) -> Result<Arc<GraphicsPipeline>, GraphicsError> {
+        if layer == RenderLayer::Opaque {
+            return Ok(self.pipeline.clone());
+        }
+
+        let mut layer_pipelines = self
+            .layer_pipelines
+            .lock()
+            .map_err(|_| GraphicsError::vulkan("layer pipeline mutex is poisoned"))?;
+
+        if let Some(pipeline) = layer_pipelines.get(&layer) {
+            return Ok(pipeline.clone());
+        }
+
+        let mut builder = self.pipeline_builder.clone();
+        builder.apply_layer(layer);
+        let pipeline = builder.build_with_layout(self.device.clone(), self.layout.clone())?;
+        layer_pipelines.insert(layer, pipeline.clone());
+
+        Ok(pipeline)
+    }
+}
+
+/// Identifies a `DrawableSharedPart`. Drawables created with equal keys share the pipeline and
+/// shared bindables, so the key has to cover everything the shared bindables are built from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SharedKey {
+    type_id: TypeId,
+    variant: u64,
+}
+
+impl SharedKey {
+    /// For drawables whose shared part is always the same, usually `T` is the drawable itself.
+    pub fn of<T: 'static>() -> Self {
+        Self {
+            type_id: TypeId::of::<T>(),
+            variant: 0,
+        }
+    }
+
+    /// For drawables whose shared part depends on parameters, e.g. the dimensions of a grid.
+    pub fn with<T: 'static>(parameters: impl Hash) -> Self {
+        let mut hasher = DefaultHasher::new();
+        parameters.hash(&mut hasher);
+
+        Self {
+            type_id: TypeId::of::<T>(),
+            variant: hasher.finish(),
+        }
+    }
+}
+
+/// Shared parts of the drawables that are still alive.
+#[derive(Default)]
+pub struct SharedPartCache {
+    parts: Mutex<HashMap<SharedKey, Weak<DrawableSharedPart>>>,
+}
+
+impl SharedPartCache {
+    pub fn get(&self, key: &SharedKey) -> Option<Arc<DrawableSharedPart>> {
+        self.parts.lock().ok()?.get(key)?.upgrade()
+    }
+
+    /// Also drops the entries of shared parts that no drawable uses anymore.
+    pub fn insert(&self, key: SharedKey, part: &Arc<DrawableSharedPart>) {
+        if let Ok(mut parts) = self.parts.lock() {
+            parts.retain(|_, p| p.strong_count() > 0);
+            parts.insert(key, Arc::downgrade(part));
+        }
+    }
+
+    /// Number of shared parts that are still alive.
+    pub fn len(&self) -> usize {
+        self.parts.lock().map_or(0, |parts| {
+            parts.values().filter(|p| p.strong_count() > 0).count()
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 pub struct GenericDrawable {
     bindables: Vec<Arc<dyn Bindable>>,
     shared_part: Arc<DrawableSharedPart>,
+    bounds: Option<Aabb>,
 }
 
 pub struct DrawableEntry {
     entry: Arc<GenericDrawable>,
-    pub registered_uid: Option<u32>,
+    /// Unregisters the drawable when the entry is dropped.
+    pub(super) registration: Option<Registration>,
 }
 
 impl DrawableEntry {
@@ -36,39 +152,32 @@ impl DrawableEntry {
     pub fn get_arc(&self) -> Arc<GenericDrawable> {
         self.entry.clone()
     }
+    /// `None` if the entry isn't registered.
+    pub fn get_handle(&self) -> Option<DrawableHandle> {
+        self.registration.as_ref().map(|p| p.get_handle())
+    }
 }
 
 impl GenericDrawable {
     pub fn new<Fn1, Fn2>(
         gfx: &super::Graphics,
-        shared_id: u32,
+        shared_key: SharedKey,
         init_bindables: Fn1,
         init_shared_bindables: Fn2,
-    ) -> DrawableEntry
+    ) -> Result<DrawableEntry, GraphicsError>
     where
-        Fn1: FnOnce() -> Vec<Arc<dyn Bindable>>,
-        Fn2: FnOnce() -> Vec<Arc<dyn Bindable>>,
+        Fn1: FnOnce() -> Result<Vec<Arc<dyn Bindable>>, GraphicsError>,
+        Fn2: FnOnce() -> Result<Vec<Arc<dyn Bindable>>, GraphicsError>,
     {
-        let shared_data = match gfx.get_shared_data_map().get(&shared_id) {
-            Some(weak) => match weak.upgrade() {
-                Some(arc) => Some(arc),
-                None => None,
-            },
-            None => None,
-        };
-
-        match shared_data {
-            Some(data) => DrawableEntry {
-                entry: Arc::new(Self {
-                    bindables: init_bindables(),
-                    shared_part: data,
-                }),
-                registered_uid: None,
-            },
+        match gfx.get_shared_parts().get(&shared_key) {
+            Some(data) => Ok(DrawableEntry {
+                entry: Arc::new(Self::with_bounds(init_bindables()?, data)),
+                registration: None,
+            }),
             None => {
                 let mut index_count = 0;
-                let bindables = init_bindables();
-                let shared_bindables = init_shared_bindables();
+                let bindables = init_bindables()?;
+                let shared_bindables = init_shared_bindables()?;
 
                 let mut pipeline_builder = PipelineBuilder::new(gfx);
 
@@ -79,23 +188,56 @@ impl GenericDrawable {
                     bindable.bind_to_pipeline(&mut pipeline_builder, &mut index_count);
                 }
 
-                let (pipeline, layout) = pipeline_builder.build(gfx.get_device());
-
-                DrawableEntry {
-                    entry: Arc::new(Self {
-                        bindables: bindables,
-                        shared_part: Arc::new(DrawableSharedPart {
-                            index_count: index_count,
-                            bindables: shared_bindables,
-                            pipeline: pipeline,
-                            layout: layout,
-                        }),
-                    }),
-                    registered_uid: None,
-                }
+                let (pipeline, layout) = pipeline_builder.clone().build(gfx.get_device())?;
+
+                let shared_part = Arc::new(DrawableSharedPart {
+                    index_count: index_count,
+                    bindables: shared_bindables,
+                    pipeline: pipeline,
+                    layout: layout,
+                    device: gfx.get_device(),
+                    pipeline_builder: pipeline_builder,
+                    layer_pipelines: Mutex::new(HashMap::new()),
+                });
+                gfx.get_shared_parts().insert(shared_key, &shared_part);
+
+                Ok(DrawableEntry {
+                    entry: Arc::new(Self::with_bounds(bindables, shared_part)),
+                    registration: None,
+                })
             }
         }
     }
+
+    /// Drawables with instances have no bounds, their vertex bounds say nothing about where the
+    /// instances end up.
+    fn with_bounds(
+        bindables: Vec<Arc<dyn Bindable>>,
+        shared_part: Arc<DrawableSharedPart>,
+    ) -> Self {
+        let all = || bindables.iter().chain(shared_part.bindables.iter());
+        let bounds = if all().any(|p| p.instance_count().is_some()) {
+            None
+        } else {
+            all().filter_map(|p| p.bounds()).reduce(|a, b| a.union(&b))
+        };
+
+        Self {
+            bindables: bindables,
+            shared_part: shared_part,
+            bounds: bounds,
+        }
+    }
+
+    /// Model space bounds of the vertices, `None` if the drawable can't be culled.
+    pub fn get_bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    /// Drawables created with the same `SharedKey` return the same part.
+    pub fn get_shared_part(&self) -> &Arc<DrawableSharedPart> {
+        &self.shared_part
+    }
 }
 
 impl Drawable for GenericDrawable {
@@ -108,8 +250,22 @@ impl Drawable for GenericDrawable {
     fn get_pipeline(&self) -> Arc<GraphicsPipeline> {
         self.shared_part.pipeline.clone()
     }
+    /// Taken from the first bindable that provides one, the count set when the shared part was
+    /// created otherwise.
     fn get_index_count(&self) -> u32 {
-        self.shared_part.index_count
+        self.bindables
+            .iter()
+            .chain(self.shared_part.bindables.iter())
+            .find_map(|p| p.index_count())
+            .unwrap_or(self.shared_part.index_count)
+    }
+    /// Taken from the first bindable that provides instances, 1 if there is none.
+    fn get_instance_count(&self) -> u32 {
+        self.bindables
+            .iter()
+            .chain(self.shared_part.bindables.iter())
+            .find_map(|p| p.instance_count())
+            .unwrap_or(1)
     }
     fn get_pipeline_layout(&self) -> Arc<PipelineLayout> {
         self.shared_part.layout.clone()
@@ -0,0 +1,162 @@
+use std::fmt;
+
+use vulkano::{
+    buffer::BufferError, format::Format, swapchain::AcquireError, sync::FlushError, LoadingError,
+    OomError, VulkanError,
+};
+
+use super::config::ConfigError;
+
+#[derive(Debug)]
+pub enum GraphicsError {
+    /// The Vulkan library could not be loaded, most likely because no driver is installed.
+    LibraryMissing(LoadingError),
+    InvalidConfig(ConfigError),
+    NoSuitableDevice,
+    /// The device forced through the config or `BATAKO_DEVICE` doesn't exist or isn't suitable.
+    RequestedDeviceUnavailable(String),
+    SurfaceLost,
+    DeviceLost,
+    OutOfMemory,
+    /// The swapchain no longer matches the surface and has to be recreated.
+    SwapchainOutOfDate,
+    UnsupportedFormat(Format),
+    /// Frames can't be captured, because the surface doesn't allow copying from its images.
+    CaptureUnsupported,
+    AssetLoad {
+        path: String,
+        reason: String,
+    },
+    /// A buffer upload was given no data, Vulkan buffers can't be empty.
+    EmptyUpload,
+    /// The pixel data doesn't match the size of the texture created from it.
+    PixelDataMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    /// A pipeline was built without a required part, names the missing part.
+    IncompletePipeline(&'static str),
+    /// Any other error reported by Vulkan.
+    Vulkan(String),
+}
+
+impl GraphicsError {
+    /// For vulkano errors that don't need to be told apart, use as `.map_err(GraphicsError::vulkan)`.
+    pub fn vulkan(error: impl fmt::Display) -> Self {
+        GraphicsError::Vulkan(error.to_string())
+    }
+
+    pub fn asset_load(path: &str, reason: impl fmt::Display) -> Self {
+        GraphicsError::AssetLoad {
+            path: String::from(path),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for GraphicsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphicsError::LibraryMissing(e) => {
+                write!(
+                    f,
+                    "the Vulkan library could not be loaded ({e}), is a driver installed?"
+                )
+            }
+            GraphicsError::InvalidConfig(e) => write!(f, "{e}"),
+            GraphicsError::NoSuitableDevice => write!(f, "no suitable graphics device was found"),
+            GraphicsError::RequestedDeviceUnavailable(device) => {
+                write!(f, "the requested {device} is not available or not suitable")
+            }
+            GraphicsError::SurfaceLost => write!(f, "the window surface was lost"),
+            GraphicsError::DeviceLost => write!(f, "the graphics device was lost"),
+            GraphicsError::OutOfMemory => write!(f, "out of memory"),
+            GraphicsError::SwapchainOutOfDate => write!(f, "the swapchain is out of date"),
+            GraphicsError::UnsupportedFormat(format) => {
+                write!(f, "the format {format:?} is not supported")
+            }
+            GraphicsError::CaptureUnsupported => {
+                write!(f, "the surface doesn't allow capturing frames")
+            }
+            GraphicsError::AssetLoad { path, reason } => {
+                write!(f, "failed to load asset {path}: {reason}")
+            }
+            GraphicsError::EmptyUpload => write!(f, "a buffer upload was given no data"),
+            GraphicsError::PixelDataMismatch { expected, actual } => {
+                write!(f, "expected {expected} bytes of pixel data, got {actual}")
+            }
+            GraphicsError::IncompletePipeline(part) => {
+                write!(f, "the pipeline was built without a {part}")
+            }
+            GraphicsError::Vulkan(e) => write!(f, "vulkan error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for GraphicsError {}
+
+impl From<LoadingError> for GraphicsError {
+    fn from(e: LoadingError) -> Self {
+        GraphicsError::LibraryMissing(e)
+    }
+}
+
+impl From<ConfigError> for GraphicsError {
+    fn from(e: ConfigError) -> Self {
+        GraphicsError::InvalidConfig(e)
+    }
+}
+
+impl From<OomError> for GraphicsError {
+    fn from(_: OomError) -> Self {
+        GraphicsError::OutOfMemory
+    }
+}
+
+impl From<VulkanError> for GraphicsError {
+    fn from(e: VulkanError) -> Self {
+        match e {
+            VulkanError::OutOfHostMemory | VulkanError::OutOfDeviceMemory => {
+                GraphicsError::OutOfMemory
+            }
+            VulkanError::DeviceLost => GraphicsError::DeviceLost,
+            VulkanError::SurfaceLost => GraphicsError::SurfaceLost,
+            VulkanError::OutOfDate => GraphicsError::SwapchainOutOfDate,
+            e => GraphicsError::vulkan(e),
+        }
+    }
+}
+
+impl From<AcquireError> for GraphicsError {
+    fn from(e: AcquireError) -> Self {
+        match e {
+            AcquireError::OomError(_) => GraphicsError::OutOfMemory,
+            AcquireError::DeviceLost => GraphicsError::DeviceLost,
+            AcquireError::SurfaceLost => GraphicsError::SurfaceLost,
+            AcquireError::OutOfDate => GraphicsError::SwapchainOutOfDate,
+            e => GraphicsError::vulkan(e),
+        }
+    }
+}
+
+impl From<FlushError> for GraphicsError {
+    fn from(e: FlushError) -> Self {
+        match e {
+            FlushError::OomError(_) => GraphicsError::OutOfMemory,
+            FlushError::DeviceLost => GraphicsError::DeviceLost,
+            FlushError::SurfaceLost => GraphicsError::SurfaceLost,
+            FlushError::OutOfDate => GraphicsError::SwapchainOutOfDate,
+            e => GraphicsError::vulkan(e),
+        }
+    }
+}
+
+impl From<BufferError> for GraphicsError {
+    fn from(e: BufferError) -> Self {
+        match e {
+            BufferError::AllocError(_) => GraphicsError::OutOfMemory,
+            BufferError::VulkanError(e) => e.into(),
+            e => GraphicsError::vulkan(e),
+        }
+    }
+}
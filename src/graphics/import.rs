@@ -0,0 +1,121 @@
+use std::io::Cursor;
+
+use cgmath::{Matrix4, SquareMatrix};
+
+use super::{error::GraphicsError, mesh::MeshData};
+
+pub mod gltf;
+pub mod obj;
+
+/// 8 bit srgb rgba pixels, row by row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl ImageData {
+    /// Any png that decodes to 8 bits per channel, missing channels are filled in.
+    pub fn load_png(path: &str) -> Result<Self, GraphicsError> {
+        let bytes = std::fs::read(path).map_err(|e| GraphicsError::asset_load(path, e))?;
+        let mut decoder = png::Decoder::new(Cursor::new(bytes));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder
+            .read_info()
+            .map_err(|e| GraphicsError::asset_load(path, e))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|e| GraphicsError::asset_load(path, e))?;
+        buffer.truncate(info.buffer_size());
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|p| [*p, *p, *p, 255]).collect(),
+            color_type => {
+                return Err(GraphicsError::asset_load(
+                    path,
+                    format!("{color_type:?} pngs are not supported"),
+                ))
+            }
+        };
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels: pixels,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialData {
+    /// Multiplied with the texture, if there is one.
+    pub base_color: [f32; 4],
+    /// Index into `ModelData::images`.
+    pub base_color_texture: Option<usize>,
+    /// Drawn on the transparent layer.
+    pub blend: bool,
+}
+
+impl Default for MaterialData {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0; 4],
+            base_color_texture: None,
+            blend: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrimitiveData {
+    pub mesh: MeshData,
+    /// Index into `ModelData::materials`, the default material is used if there's none.
+    pub material: Option<usize>,
+}
+
+/// Places a mesh in the model.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeData {
+    /// Index into `ModelData::meshes`.
+    pub mesh: usize,
+    /// Relative to the model, parent transforms are already applied.
+    pub transform: Matrix4<f32>,
+}
+
+/// An imported model on the cpu, ready to be turned into drawables.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelData {
+    /// Each mesh is made of one or more primitives with their own material.
+    pub meshes: Vec<Vec<PrimitiveData>>,
+    pub materials: Vec<MaterialData>,
+    pub images: Vec<ImageData>,
+    pub nodes: Vec<NodeData>,
+}
+
+impl ModelData {
+    /// A single mesh with the default material, e.g. one of the generated shapes.
+    pub fn from_mesh(mesh: MeshData) -> Self {
+        Self {
+            meshes: vec![vec![PrimitiveData {
+                mesh: mesh,
+                material: None,
+            }]],
+            nodes: vec![NodeData {
+                mesh: 0,
+                transform: Matrix4::identity(),
+            }],
+            ..Default::default()
+        }
+    }
+}
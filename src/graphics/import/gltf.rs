@@ -0,0 +1,254 @@
+use ::gltf::{
+    buffer::Data as BufferSource,
+    image::{Data as ImageSource, Format},
+    material::AlphaMode,
+    mesh::Mode,
+    Document, Node,
+};
+use cgmath::{Matrix4, SquareMatrix};
+
+use crate::graphics::{
+    error::GraphicsError,
+    mesh::{MeshData, MeshVertex},
+};
+
+use super::{ImageData, MaterialData, ModelData, NodeData, PrimitiveData};
+
+/// Loads a `.gltf` with its `.bin` and image files, or a `.glb`.
+/// Only triangle primitives and base color materials are imported.
+pub fn load(path: &str) -> Result<ModelData, GraphicsError> {
+    let (document, buffers, images) =
+        ::gltf::import(path).map_err(|e| GraphicsError::asset_load(path, e))?;
+    convert(path, &document, &buffers, images)
+}
+
+/// `path` is only used in errors.
+fn convert(
+    path: &str,
+    document: &Document,
+    buffers: &[BufferSource],
+    images: Vec<ImageSource>,
+) -> Result<ModelData, GraphicsError> {
+    let meshes = document
+        .meshes()
+        .map(|mesh| {
+            mesh.primitives()
+                .filter(|primitive| {
+                    let triangles = primitive.mode() == Mode::Triangles;
+                    if !triangles {
+                        let mode = primitive.mode();
+                        log::warn!(
+                            "{path}: skipped {mode:?} primitive of mesh {}",
+                            mesh.index()
+                        );
+                    }
+                    triangles
+                })
+                .map(|primitive| {
+                    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+                    let positions: Vec<[f32; 3]> = reader
+                        .read_positions()
+                        .ok_or_else(|| {
+                            GraphicsError::asset_load(path, "a primitive has no positions")
+                        })?
+                        .collect();
+                    let normals: Option<Vec<[f32; 3]>> =
+                        reader.read_normals().map(Iterator::collect);
+                    let uvs: Option<Vec<[f32; 2]>> = reader
+                        .read_tex_coords(0)
+                        .map(|uvs| uvs.into_f32().collect());
+                    check_count(path, "normals", normals.as_deref(), positions.len())?;
+                    check_count(path, "uvs", uvs.as_deref(), positions.len())?;
+
+                    let vertices = positions
+                        .iter()
+                        .enumerate()
+                        .map(|(i, position)| MeshVertex {
+                            position: *position,
+                            normal: normals.as_ref().map_or([0.0; 3], |p| p[i]),
+                            uv: uvs.as_ref().map_or([0.0; 2], |p| p[i]),
+                        })
+                        .collect();
+                    let indices: Vec<u32> = match reader.read_indices() {
+                        Some(indices) => indices.into_u32().collect(),
+                        None => (0..positions.len() as u32).collect(),
+                    };
+                    if let Some(index) = indices.iter().find(|p| **p as usize >= positions.len()) {
+                        return Err(GraphicsError::asset_load(
+                            path,
+                            format!(
+                                "index {index} is out of range for {} vertices",
+                                positions.len()
+                            ),
+                        ));
+                    }
+
+                    let mut data = MeshData {
+                        vertices: vertices,
+                        indices: indices,
+                    };
+                    if normals.is_none() {
+                        data.compute_normals();
+                    }
+
+                    Ok(PrimitiveData {
+                        mesh: data,
+                        material: primitive.material().index(),
+                    })
+                })
+                .collect::<Result<Vec<_>, GraphicsError>>()
+        })
+        .collect::<Result<Vec<_>, GraphicsError>>()?;
+
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            MaterialData {
+                base_color: pbr.base_color_factor(),
+                base_color_texture: pbr
+                    .base_color_texture()
+                    .map(|info| info.texture().source().index()),
+                blend: material.alpha_mode() == AlphaMode::Blend,
+            }
+        })
+        .collect();
+
+    let images = images
+        .into_iter()
+        .map(|image| to_rgba(path, image))
+        .collect::<Result<Vec<_>, GraphicsError>>()?;
+
+    Ok(ModelData {
+        meshes: meshes,
+        materials: materials,
+        images: images,
+        nodes: collect_nodes(document),
+    })
+}
+
+/// Vertex attributes have to cover every position, accessors of a broken file may be shorter.
+fn check_count<T>(
+    path: &str,
+    attribute: &str,
+    values: Option<&[T]>,
+    vertex_count: usize,
+) -> Result<(), GraphicsError> {
+    match values {
+        Some(values) if values.len() < vertex_count => Err(GraphicsError::asset_load(
+            path,
+            format!("{} {attribute} for {vertex_count} vertices", values.len()),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Flattens the default scene, or the first one if there's no default.
+fn collect_nodes(document: &Document) -> Vec<NodeData> {
+    fn visit(node: Node, parent: Matrix4<f32>, nodes: &mut Vec<NodeData>) {
+        let transform = parent * Matrix4::from(node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            nodes.push(NodeData {
+                mesh: mesh.index(),
+                transform: transform,
+            });
+        }
+        for child in node.children() {
+            visit(child, transform, nodes);
+        }
+    }
+
+    let mut nodes = Vec::new();
+    if let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        for node in scene.nodes() {
+            visit(node, Matrix4::identity(), &mut nodes);
+        }
+    }
+    nodes
+}
+
+fn to_rgba(path: &str, image: ImageSource) -> Result<ImageData, GraphicsError> {
+    let channels = match image.format {
+        Format::R8 => 1,
+        Format::R8G8 => 2,
+        Format::R8G8B8 => 3,
+        Format::R8G8B8A8 => 4,
+        format => {
+            return Err(GraphicsError::asset_load(
+                path,
+                format!("{format:?} images are not supported"),
+            ))
+        }
+    };
+
+    let pixels = image
+        .pixels
+        .chunks_exact(channels)
+        .flat_map(|pixel| match pixel {
+            [r] => [*r, *r, *r, 255],
+            [r, g] => [*r, *r, *r, *g],
+            [r, g, b] => [*r, *g, *b, 255],
+            [r, g, b, a] => [*r, *g, *b, *a],
+            _ => unreachable!(),
+        })
+        .collect();
+
+    Ok(ImageData {
+        width: image.width,
+        height: image.height,
+        pixels: pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One triangle whose normal accessor has `normal_count` of its three normals.
+    fn triangle(normal_count: u32) -> String {
+        // three positions followed by three normals, all f32
+        let buffer = "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAIA/\
+            AAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/";
+        format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "buffers": [{{ "byteLength": 72, "uri": "data:application/octet-stream;base64,{buffer}" }}],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                    {{ "buffer": 0, "byteOffset": 36, "byteLength": 36 }}
+                ],
+                "accessors": [
+                    {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                       "min": [0, 0, 0], "max": [1, 1, 0] }},
+                    {{ "bufferView": 1, "componentType": 5126, "count": {normal_count}, "type": "VEC3" }}
+                ],
+                "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0, "NORMAL": 1 }} }}] }}]
+            }}"#
+        )
+    }
+
+    fn convert_text(text: &str) -> Result<ModelData, GraphicsError> {
+        let (document, buffers, images) = ::gltf::import_slice(text.as_bytes()).unwrap();
+        convert("triangle.gltf", &document, &buffers, images)
+    }
+
+    #[test]
+    fn complete_accessors_load() {
+        let model = convert_text(&triangle(3)).unwrap();
+        let mesh = &model.meshes[0][0].mesh;
+
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert_eq!(mesh.vertices[2].normal, [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn truncated_accessor_is_an_error() {
+        let result = convert_text(&triangle(2));
+        assert!(matches!(result, Err(GraphicsError::AssetLoad { .. })));
+    }
+}
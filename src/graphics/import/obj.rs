@@ -0,0 +1,603 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use cgmath::{Matrix4, SquareMatrix};
+
+use crate::graphics::{
+    error::GraphicsError,
+    mesh::{MeshData, MeshVertex},
+};
+
+use super::{ImageData, MaterialData, ModelData, NodeData, PrimitiveData};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// Starts at 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Faces using the same material, as an indexed triangle list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjGroup {
+    /// Set by `usemtl`.
+    pub material: Option<String>,
+    pub mesh: MeshData,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjFile {
+    /// In the order their materials were first used.
+    pub groups: Vec<ObjGroup>,
+    /// Paths from `mtllib`, relative to the obj file.
+    pub material_libraries: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MtlMaterial {
+    pub name: String,
+    /// `Kd`
+    pub diffuse: [f32; 3],
+    /// `d`, or one minus `Tr`.
+    pub dissolve: f32,
+    /// `map_Kd`, relative to the mtl file.
+    pub diffuse_texture: Option<String>,
+}
+
+impl MtlMaterial {
+    fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            diffuse: [1.0; 3],
+            dissolve: 1.0,
+            diffuse_texture: None,
+        }
+    }
+}
+
+/// Loads an obj file with the materials of its mtl files.
+/// Missing mtl or texture files are logged and skipped.
+pub fn load(path: &str) -> Result<ModelData, GraphicsError> {
+    let text = std::fs::read_to_string(path).map_err(|e| GraphicsError::asset_load(path, e))?;
+    let obj = parse_obj(&text).map_err(|e| GraphicsError::asset_load(path, e))?;
+    let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+
+    let mut materials = Vec::new();
+    for library in &obj.material_libraries {
+        let library_path = directory.join(library);
+        let library_path = library_path.to_string_lossy();
+        let text = match std::fs::read_to_string(library_path.as_ref()) {
+            Ok(text) => text,
+            Err(e) => {
+                log::warn!("{path}: skipped material library {library_path}: {e}");
+                continue;
+            }
+        };
+        let library_directory = Path::new(library_path.as_ref())
+            .parent()
+            .unwrap_or(Path::new(""));
+
+        let mut library_materials =
+            parse_mtl(&text).map_err(|e| GraphicsError::asset_load(&library_path, e))?;
+        // texture paths are relative to the mtl file, make them usable from here
+        for material in &mut library_materials {
+            if let Some(texture) = &mut material.diffuse_texture {
+                *texture = library_directory
+                    .join(texture.as_str())
+                    .to_string_lossy()
+                    .into();
+            }
+        }
+        materials.extend(library_materials);
+    }
+
+    Ok(to_model_data(obj, &materials, |texture| {
+        ImageData::load_png(texture)
+            .map_err(|e| log::warn!("{path}: skipped texture, {e}"))
+            .ok()
+    }))
+}
+
+/// Puts all groups into a single mesh, materials that aren't found fall back to the default.
+/// `load_image` is called once for every texture used.
+pub fn to_model_data(
+    obj: ObjFile,
+    materials: &[MtlMaterial],
+    mut load_image: impl FnMut(&str) -> Option<ImageData>,
+) -> ModelData {
+    let mut data = ModelData::default();
+    let mut material_indices = HashMap::new();
+    let mut image_indices = HashMap::new();
+
+    let primitives = obj
+        .groups
+        .into_iter()
+        .map(|group| {
+            let material = group.material.and_then(|name| {
+                if let Some(index) = material_indices.get(&name) {
+                    return Some(*index);
+                }
+                let Some(material) = materials.iter().rev().find(|p| p.name == name) else {
+                    log::warn!("material {name} was not found");
+                    return None;
+                };
+
+                let texture = material.diffuse_texture.as_ref().and_then(|path| {
+                    if let Some(index) = image_indices.get(path) {
+                        return Some(*index);
+                    }
+                    let image = load_image(path)?;
+                    data.images.push(image);
+                    image_indices.insert(path.clone(), data.images.len() - 1);
+                    Some(data.images.len() - 1)
+                });
+
+                let [r, g, b] = material.diffuse;
+                data.materials.push(MaterialData {
+                    base_color: [r, g, b, material.dissolve],
+                    base_color_texture: texture,
+                    blend: material.dissolve < 1.0,
+                });
+                material_indices.insert(name, data.materials.len() - 1);
+                Some(data.materials.len() - 1)
+            });
+
+            PrimitiveData {
+                mesh: group.mesh,
+                material: material,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if !primitives.is_empty() {
+        data.meshes.push(primitives);
+        data.nodes.push(NodeData {
+            mesh: 0,
+            transform: Matrix4::identity(),
+        });
+    }
+
+    data
+}
+
+/// Vertices are de-duplicated per group, so faces sharing a position, uv and normal share the
+/// vertex. Polygons are triangulated as fans. Groups with vertices missing a normal get computed
+/// normals instead.
+pub fn parse_obj(text: &str) -> Result<ObjFile, ParseError> {
+    struct Group {
+        material: Option<String>,
+        mesh: MeshData,
+        vertex_indices: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+        missing_normals: bool,
+    }
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut material_libraries = Vec::new();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut current_group: Option<usize> = None;
+    let mut current_material: Option<String> = None;
+
+    for (line_index, line) in text.lines().enumerate() {
+        let error = |message: String| ParseError {
+            line: line_index + 1,
+            message: message,
+        };
+
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => positions.push(parse_floats::<3>(&arguments, 0.0).map_err(error)?),
+            "vt" => {
+                let [u, v] = parse_floats::<2>(&arguments, 0.0).map_err(error)?;
+                // obj puts the origin at the bottom left, vulkan at the top left
+                uvs.push([u, 1.0 - v]);
+            }
+            "vn" => normals.push(parse_floats::<3>(&arguments, 0.0).map_err(error)?),
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(error(format!(
+                        "a face needs at least 3 vertices, got {}",
+                        arguments.len()
+                    )));
+                }
+
+                let group_index = match current_group {
+                    Some(index) => index,
+                    None => {
+                        let index = groups
+                            .iter()
+                            .position(|p| p.material == current_material)
+                            .unwrap_or_else(|| {
+                                groups.push(Group {
+                                    material: current_material.clone(),
+                                    mesh: MeshData::default(),
+                                    vertex_indices: HashMap::new(),
+                                    missing_normals: false,
+                                });
+                                groups.len() - 1
+                            });
+                        current_group = Some(index);
+                        index
+                    }
+                };
+                let group = &mut groups[group_index];
+
+                let mut face = Vec::with_capacity(arguments.len());
+                for argument in &arguments {
+                    let key =
+                        parse_face_vertex(argument, [positions.len(), uvs.len(), normals.len()])
+                            .map_err(error)?;
+
+                    let index = match group.vertex_indices.get(&key) {
+                        Some(index) => *index,
+                        None => {
+                            let (position, uv, normal) = key;
+                            group.missing_normals |= normal.is_none();
+                            group.mesh.vertices.push(MeshVertex {
+                                position: positions[position],
+                                normal: normal.map_or([0.0; 3], |p| normals[p]),
+                                uv: uv.map_or([0.0; 2], |p| uvs[p]),
+                            });
+                            let index = group.mesh.vertices.len() as u32 - 1;
+                            group.vertex_indices.insert(key, index);
+                            index
+                        }
+                    };
+                    face.push(index);
+                }
+
+                for i in 1..face.len() - 1 {
+                    group.mesh.indices.extend([face[0], face[i], face[i + 1]]);
+                }
+            }
+            "usemtl" => {
+                current_material = arguments.first().map(|p| String::from(*p));
+                current_group = None;
+            }
+            "mtllib" => material_libraries.extend(arguments.iter().map(|p| String::from(*p))),
+            // objects, groups, smoothing groups and anything else don't matter here
+            _ => {}
+        }
+    }
+
+    Ok(ObjFile {
+        groups: groups
+            .into_iter()
+            .map(|mut group| {
+                if group.missing_normals {
+                    group.mesh.compute_normals();
+                }
+                ObjGroup {
+                    material: group.material,
+                    mesh: group.mesh,
+                }
+            })
+            .collect(),
+        material_libraries: material_libraries,
+    })
+}
+
+/// Only the diffuse color, transparency and diffuse texture are read.
+pub fn parse_mtl(text: &str) -> Result<Vec<MtlMaterial>, ParseError> {
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+
+    for (line_index, line) in text.lines().enumerate() {
+        let error = |message: String| ParseError {
+            line: line_index + 1,
+            message: message,
+        };
+
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            let name = arguments
+                .first()
+                .ok_or_else(|| error(String::from("newmtl needs a name")))?;
+            materials.push(MtlMaterial::new(name));
+            continue;
+        }
+
+        let Some(material) = materials.last_mut() else {
+            continue;
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_floats::<3>(&arguments, 0.0).map_err(error)?,
+            "d" => material.dissolve = parse_floats::<1>(&arguments, 1.0).map_err(error)?[0],
+            "Tr" => material.dissolve = 1.0 - parse_floats::<1>(&arguments, 0.0).map_err(error)?[0],
+            // the file name comes after any options
+            "map_Kd" => material.diffuse_texture = arguments.last().map(|p| String::from(*p)),
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+/// Reads up to `N` floats, missing ones are `default`. Extra values like `w` are ignored.
+fn parse_floats<const N: usize>(arguments: &[&str], default: f32) -> Result<[f32; N], String> {
+    let mut values = [default; N];
+    for (value, argument) in values.iter_mut().zip(arguments) {
+        *value = argument
+            .parse()
+            .map_err(|_| format!("{argument} is not a number"))?;
+    }
+    Ok(values)
+}
+
+/// Turns `v`, `v/vt`, `v//vn` or `v/vt/vn` into zero based indices, negative indices count back
+/// from the given element counts.
+fn parse_face_vertex(
+    vertex: &str,
+    counts: [usize; 3],
+) -> Result<(usize, Option<usize>, Option<usize>), String> {
+    let mut parts = vertex.split('/');
+    let mut next_index = |count: usize, name: &str| -> Result<Option<usize>, String> {
+        let part = match parts.next() {
+            Some(part) if !part.is_empty() => part,
+            _ => return Ok(None),
+        };
+        let index: i64 = part
+            .parse()
+            .map_err(|_| format!("{part} is not a valid {name} index"))?;
+        let resolved = if index < 0 {
+            count as i64 + index
+        } else {
+            index - 1
+        };
+        if resolved < 0 || resolved >= count as i64 {
+            return Err(format!("{name} index {index} is out of range"));
+        }
+        Ok(Some(resolved as usize))
+    };
+
+    let position = next_index(counts[0], "position")?
+        .ok_or_else(|| format!("{vertex} has no position index"))?;
+    let uv = next_index(counts[1], "uv")?;
+    let normal = next_index(counts[2], "normal")?;
+
+    Ok((position, uv, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+";
+
+    fn single_group(text: &str) -> MeshData {
+        let mut obj = parse_obj(text).unwrap();
+        assert_eq!(obj.groups.len(), 1);
+        obj.groups.remove(0).mesh
+    }
+
+    #[test]
+    fn shared_vertices_are_deduplicated() {
+        let mesh = single_group(&format!(
+            "{SQUARE}f 1/1/1 2/2/1 3/3/1\nf 1/1/1 3/3/1 4/4/1\n"
+        ));
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(
+            mesh.vertices[0],
+            MeshVertex {
+                position: [0.0, 0.0, 0.0],
+                normal: [0.0, 0.0, 1.0],
+                // flipped to put the origin at the top left
+                uv: [0.0, 1.0],
+            }
+        );
+    }
+
+    #[test]
+    fn same_position_with_other_uv_is_a_new_vertex() {
+        let mesh = single_group(&format!(
+            "{SQUARE}f 1/1/1 2/2/1 3/3/1\nf 1/2/1 3/3/1 4/4/1\n"
+        ));
+
+        assert_eq!(mesh.vertices.len(), 5);
+        assert_eq!(mesh.indices, [0, 1, 2, 3, 2, 4]);
+        assert_eq!(mesh.vertices[3].position, mesh.vertices[0].position);
+    }
+
+    #[test]
+    fn polygons_are_fan_triangulated() {
+        let quad = single_group(&format!("{SQUARE}f 1 2 3 4\n"));
+        assert_eq!(quad.indices, [0, 1, 2, 0, 2, 3]);
+
+        let pentagon = single_group(
+            "v 1 0 0\nv 0.3 0.95 0\nv -0.8 0.6 0\nv -0.8 -0.6 0\nv 0.3 -0.95 0\nf 1 2 3 4 5\n",
+        );
+        assert_eq!(pentagon.vertices.len(), 5);
+        assert_eq!(pentagon.indices, [0, 1, 2, 0, 2, 3, 0, 3, 4]);
+    }
+
+    #[test]
+    fn missing_normals_are_computed() {
+        let mesh = single_group(&format!("{SQUARE}f 1/1 2/2 3/3 4/4\n"));
+
+        for vertex in &mesh.vertices {
+            assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn negative_indices_count_from_the_end() {
+        let relative = single_group(&format!("{SQUARE}f -4/-4/-1 -3/-3/-1 -2/-2/-1\n"));
+        let absolute = single_group(&format!("{SQUARE}f 1/1/1 2/2/1 3/3/1\n"));
+
+        assert_eq!(relative, absolute);
+    }
+
+    #[test]
+    fn out_of_range_indices_are_errors() {
+        let error = parse_obj(&format!("{SQUARE}f 1 2 5\n")).unwrap_err();
+        // the square takes up lines 1 to 10
+        assert_eq!(error.line, 11);
+        assert!(error.message.contains("out of range"), "{error}");
+
+        assert!(parse_obj(&format!("{SQUARE}f 0 1 2\n")).is_err());
+        assert!(parse_obj(&format!("{SQUARE}f -5 1 2\n")).is_err());
+        assert!(parse_obj(&format!("{SQUARE}f 1/5 2 3\n")).is_err());
+        assert!(parse_obj(&format!("{SQUARE}f 1//2 2 3\n")).is_err());
+        assert!(parse_obj(&format!("{SQUARE}f 1 2\n")).is_err());
+        assert!(parse_obj(&format!("{SQUARE}f 1 x 2\n")).is_err());
+    }
+
+    #[test]
+    fn faces_are_grouped_by_material() {
+        let text = format!(
+            "{SQUARE}mtllib a.mtl b.mtl
+f 1 2 3
+usemtl red
+f 1 2 3
+usemtl blue
+f 1 3 4
+usemtl red
+f 2 3 4
+"
+        );
+        let obj = parse_obj(&text).unwrap();
+
+        assert_eq!(obj.material_libraries, ["a.mtl", "b.mtl"]);
+        let materials: Vec<_> = obj.groups.iter().map(|p| p.material.as_deref()).collect();
+        assert_eq!(materials, [None, Some("red"), Some("blue")]);
+
+        let red = &obj.groups[1].mesh;
+        assert_eq!(red.vertices.len(), 4);
+        assert_eq!(red.indices, [0, 1, 2, 1, 2, 3]);
+        assert_eq!(obj.groups[2].mesh.indices, [0, 1, 2]);
+    }
+
+    #[test]
+    fn mtl_materials_are_parsed() {
+        let materials = parse_mtl(
+            "# comment
+Kd 0 1 0
+newmtl textured
+Kd 1 0 0
+d 0.5
+map_Kd -s 1 1 1 textures/a.png
+newmtl plain
+Tr 0.25
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            materials,
+            [
+                MtlMaterial {
+                    name: String::from("textured"),
+                    diffuse: [1.0, 0.0, 0.0],
+                    dissolve: 0.5,
+                    diffuse_texture: Some(String::from("textures/a.png")),
+                },
+                MtlMaterial {
+                    name: String::from("plain"),
+                    diffuse: [1.0; 3],
+                    dissolve: 0.75,
+                    diffuse_texture: None,
+                },
+            ]
+        );
+
+        let error = parse_mtl("newmtl a\nnewmtl\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(parse_mtl("newmtl a\nKd 1 red 0\n").is_err());
+    }
+
+    #[test]
+    fn model_data_has_one_primitive_per_group() {
+        let obj = parse_obj(&format!(
+            "{SQUARE}usemtl a\nf 1 2 3\nusemtl b\nf 1 3 4\nusemtl missing\nf 2 3 4\nusemtl a2\nf 1 2 4\n"
+        ))
+        .unwrap();
+        let material = |name: &str, dissolve: f32, texture: Option<&str>| MtlMaterial {
+            name: String::from(name),
+            diffuse: [1.0, 0.0, 0.0],
+            dissolve: dissolve,
+            diffuse_texture: texture.map(String::from),
+        };
+        let materials = [
+            material("a", 0.5, Some("a.png")),
+            material("b", 1.0, None),
+            material("a2", 1.0, Some("a.png")),
+        ];
+
+        let mut loaded = Vec::new();
+        let data = to_model_data(obj, &materials, |path| {
+            loaded.push(String::from(path));
+            Some(ImageData {
+                width: 1,
+                height: 1,
+                pixels: vec![255; 4],
+            })
+        });
+
+        // both materials use the same texture
+        assert_eq!(loaded, ["a.png"]);
+        assert_eq!(data.images.len(), 1);
+
+        assert_eq!(
+            data.materials,
+            [
+                MaterialData {
+                    base_color: [1.0, 0.0, 0.0, 0.5],
+                    base_color_texture: Some(0),
+                    blend: true,
+                },
+                MaterialData {
+                    base_color: [1.0, 0.0, 0.0, 1.0],
+                    base_color_texture: None,
+                    blend: false,
+                },
+                MaterialData {
+                    base_color: [1.0, 0.0, 0.0, 1.0],
+                    base_color_texture: Some(0),
+                    blend: false,
+                },
+            ]
+        );
+
+        assert_eq!(data.meshes.len(), 1);
+        let primitive_materials: Vec<_> = data.meshes[0].iter().map(|p| p.material).collect();
+        assert_eq!(primitive_materials, [Some(0), Some(1), None, Some(2)]);
+        assert_eq!(data.nodes.len(), 1);
+        assert_eq!(data.nodes[0].mesh, 0);
+    }
+
+    #[test]
+    fn empty_files_have_no_nodes() {
+        let data = to_model_data(parse_obj("# nothing\n").unwrap(), &[], |_| None);
+
+        assert_eq!(data, ModelData::default());
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use cgmath::{InnerSpace, Vector3};
+use vulkano::{buffer::BufferContents, pipeline::graphics::vertex_input::Vertex};
+
+use super::{
+    bindable::{Bindable, IndexBuffer, VertexBuffer},
+    bounds::{Aabb, PositionedVertex},
+    error::GraphicsError,
+    Graphics,
+};
+
+mod shapes;
+
+/// Vertex layout of the `mesh` shaders.
+#[derive(BufferContents, Vertex, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct MeshVertex {
+    #[format(R32G32B32_SFLOAT)]
+    pub position: [f32; 3],
+    #[format(R32G32B32_SFLOAT)]
+    pub normal: [f32; 3],
+    #[format(R32G32_SFLOAT)]
+    pub uv: [f32; 2],
+}
+
+impl PositionedVertex for MeshVertex {
+    fn position(&self) -> [f32; 3] {
+        self.position
+    }
+}
+
+/// Indexed triangle list on the cpu.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshData {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
+
+/// Indexed line list on the cpu, pipelines drawing it need `PrimitiveTopology::LineList`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineData {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    /// The vertex and index buffer, ready to be used as bindables of a drawable using the `mesh`
+    /// shaders.
+    pub fn bindables(&self, gfx: &Graphics) -> Result<Vec<Arc<dyn Bindable>>, GraphicsError> {
+        Ok(vec![self.vertex_buffer(gfx)?, self.index_buffer(gfx)?])
+    }
+
+    pub fn vertex_buffer(
+        &self,
+        gfx: &Graphics,
+    ) -> Result<Arc<VertexBuffer<MeshVertex>>, GraphicsError> {
+        VertexBuffer::new_bounded(gfx, self.vertices.clone())
+    }
+
+    /// For shaders with a different vertex layout. The buffer keeps the bounds of the mesh.
+    pub fn vertex_buffer_with<T>(
+        &self,
+        gfx: &Graphics,
+        convert: impl Fn(&MeshVertex) -> T,
+    ) -> Result<Arc<VertexBuffer<T>>, GraphicsError>
+    where
+        T: Vertex + BufferContents,
+    {
+        VertexBuffer::with_bounds(
+            gfx,
+            self.vertices.iter().map(convert).collect(),
+            self.bounds(),
+        )
+    }
+
+    pub fn index_buffer(&self, gfx: &Graphics) -> Result<Arc<IndexBuffer>, GraphicsError> {
+        IndexBuffer::new(gfx, self.indices.clone())
+    }
+
+    /// `None` if there are no vertices.
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|p| p.position))
+    }
+
+    /// Sets every vertex normal to the area weighted average of the triangles using it.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vector3::new(0.0, 0.0, 0.0); self.vertices.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] =
+                [0, 1, 2].map(|i| Vector3::from(self.vertices[triangle[i] as usize].position));
+            // not normalized, so bigger triangles weigh more
+            let normal = (b - a).cross(c - a);
+            for &index in triangle {
+                normals[index as usize] += normal;
+            }
+        }
+
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            vertex.normal = if normal.magnitude2() > 0.0 {
+                normal.normalize().into()
+            } else {
+                [0.0, 0.0, 1.0]
+            };
+        }
+    }
+}
+
+impl LineData {
+    /// The vertex and index buffer, like `MeshData::bindables`.
+    pub fn bindables(&self, gfx: &Graphics) -> Result<Vec<Arc<dyn Bindable>>, GraphicsError> {
+        Ok(vec![
+            VertexBuffer::new_bounded(gfx, self.vertices.clone())?,
+            IndexBuffer::new(gfx, self.indices.clone())?,
+        ])
+    }
+
+    /// `None` if there are no vertices.
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|p| p.position))
+    }
+}
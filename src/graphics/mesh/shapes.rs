@@ -0,0 +1,449 @@
+use std::{collections::HashMap, f32::consts::PI};
+
+use cgmath::{InnerSpace, Vector2, Vector3};
+
+use super::{LineData, MeshData, MeshVertex};
+
+// All shapes are centered on the origin with y pointing up. Triangles are counter clockwise when
+// seen from the outside, like the cube's, and v grows downwards in the textures.
+
+impl MeshData {
+    /// Lies in the xz plane facing +y, split into `segments` quads along x and z.
+    pub fn plane(size: Vector2<f32>, segments: Vector2<u32>) -> Self {
+        let segments = segments.map(|p| p.max(1));
+        let mut mesh = Self::default();
+        push_surface(&mut mesh, segments.y, segments.x, |row, column| {
+            let u = column as f32 / segments.x as f32;
+            let v = row as f32 / segments.y as f32;
+            MeshVertex {
+                position: [(u - 0.5) * size.x, 0.0, (v - 0.5) * size.y],
+                normal: [0.0, 1.0, 0.0],
+                uv: [u, v],
+            }
+        });
+        mesh
+    }
+
+    /// Every face has its own vertices, so the normals are flat. Each face is textured with the
+    /// whole texture.
+    pub fn cuboid(size: Vector3<f32>) -> Self {
+        let x = Vector3::unit_x() * size.x;
+        let y = Vector3::unit_y() * size.y;
+        let z = Vector3::unit_z() * size.z;
+
+        // normal, then the directions u and v grow in
+        let faces = [
+            (Vector3::unit_y(), x, z),
+            (-Vector3::unit_y(), x, -z),
+            (Vector3::unit_x(), -z, -y),
+            (-Vector3::unit_x(), z, -y),
+            (Vector3::unit_z(), x, -y),
+            (-Vector3::unit_z(), -x, -y),
+        ];
+
+        let mut mesh = Self::default();
+        for (normal, u_axis, v_axis) in faces {
+            let center =
+                Vector3::new(normal.x * size.x, normal.y * size.y, normal.z * size.z) / 2.0;
+            push_surface(&mut mesh, 1, 1, |row, column| {
+                let (u, v) = (column as f32, row as f32);
+                MeshVertex {
+                    position: (center + u_axis * (u - 0.5) + v_axis * (v - 0.5)).into(),
+                    normal: normal.into(),
+                    uv: [u, v],
+                }
+            });
+        }
+        mesh
+    }
+
+    /// `segments` around the y axis, `rings` from pole to pole.
+    pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Self {
+        let segments = segments.max(3);
+        let rings = rings.max(2);
+        let mut mesh = Self::default();
+        push_surface(&mut mesh, rings, segments, |row, column| {
+            let u = column as f32 / segments as f32;
+            let v = row as f32 / rings as f32;
+            let normal = around_y(u, v * PI);
+            MeshVertex {
+                position: (normal * radius).into(),
+                normal: normal.into(),
+                uv: [u, v],
+            }
+        });
+        mesh
+    }
+
+    /// An icosahedron with every triangle split into four `subdivisions` times, which spreads the
+    /// vertices more evenly than `uv_sphere`. The uvs are spherical, so textures are slightly
+    /// distorted along the seam at -z.
+    pub fn icosphere(radius: f32, subdivisions: u32) -> Self {
+        let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+        let mut positions: Vec<Vector3<f32>> = [
+            [-1.0, t, 0.0],
+            [1.0, t, 0.0],
+            [-1.0, -t, 0.0],
+            [1.0, -t, 0.0],
+            [0.0, -1.0, t],
+            [0.0, 1.0, t],
+            [0.0, -1.0, -t],
+            [0.0, 1.0, -t],
+            [t, 0.0, -1.0],
+            [t, 0.0, 1.0],
+            [-t, 0.0, -1.0],
+            [-t, 0.0, 1.0],
+        ]
+        .map(|p| Vector3::from(p).normalize())
+        .to_vec();
+
+        #[rustfmt::skip]
+        let mut triangles: Vec<[u32; 3]> = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            let mut midpoints = HashMap::new();
+            let mut midpoint = |a: u32, b: u32| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    let point = (positions[a as usize] + positions[b as usize]).normalize();
+                    positions.push(point);
+                    positions.len() as u32 - 1
+                })
+            };
+
+            triangles = triangles
+                .iter()
+                .flat_map(|&[a, b, c]| {
+                    let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                    [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+
+        Self {
+            vertices: positions
+                .iter()
+                .map(|normal| MeshVertex {
+                    position: (normal * radius).into(),
+                    normal: (*normal).into(),
+                    uv: [
+                        0.5 + normal.x.atan2(normal.z) / (2.0 * PI),
+                        normal.y.clamp(-1.0, 1.0).acos() / PI,
+                    ],
+                })
+                .collect(),
+            indices: triangles.into_iter().flatten().collect(),
+        }
+    }
+
+    /// Standing on the y axis, with caps on both ends.
+    pub fn cylinder(radius: f32, height: f32, segments: u32) -> Self {
+        let segments = segments.max(3);
+        let mut mesh = Self::default();
+        push_surface(&mut mesh, 1, segments, |row, column| {
+            let u = column as f32 / segments as f32;
+            let normal = around_y(u, PI / 2.0);
+            MeshVertex {
+                position: [
+                    normal.x * radius,
+                    (0.5 - row as f32) * height,
+                    normal.z * radius,
+                ],
+                normal: normal.into(),
+                uv: [u, row as f32],
+            }
+        });
+        push_cap(&mut mesh, radius, height / 2.0, segments, true);
+        push_cap(&mut mesh, radius, -height / 2.0, segments, false);
+        mesh
+    }
+
+    /// Points up the y axis, with a cap at the bottom.
+    pub fn cone(radius: f32, height: f32, segments: u32) -> Self {
+        let segments = segments.max(3);
+        let mut mesh = Self::default();
+        push_surface(&mut mesh, 1, segments, |row, column| {
+            let u = column as f32 / segments as f32;
+            let around = around_y(u, PI / 2.0);
+            // perpendicular to the slope
+            let normal = Vector3::new(around.x * height, radius, around.z * height).normalize();
+            let ring_radius = row as f32 * radius;
+            MeshVertex {
+                position: [
+                    around.x * ring_radius,
+                    (0.5 - row as f32) * height,
+                    around.z * ring_radius,
+                ],
+                normal: normal.into(),
+                uv: [u, row as f32],
+            }
+        });
+        push_cap(&mut mesh, radius, -height / 2.0, segments, false);
+        mesh
+    }
+
+    /// Lies in the xz plane. `major_radius` is the distance from the center to the middle of the
+    /// tube, `minor_radius` the radius of the tube.
+    pub fn torus(
+        major_radius: f32,
+        minor_radius: f32,
+        major_segments: u32,
+        minor_segments: u32,
+    ) -> Self {
+        let major_segments = major_segments.max(3);
+        let minor_segments = minor_segments.max(3);
+        let mut mesh = Self::default();
+        push_surface(&mut mesh, minor_segments, major_segments, |row, column| {
+            let u = column as f32 / major_segments as f32;
+            let v = row as f32 / minor_segments as f32;
+            let outwards = around_y(u, PI / 2.0);
+            // starts on the outside of the ring and goes down first
+            let (sin, cos) = (v * 2.0 * PI).sin_cos();
+            let normal = outwards * cos - Vector3::unit_y() * sin;
+            MeshVertex {
+                position: (outwards * major_radius + normal * minor_radius).into(),
+                normal: normal.into(),
+                uv: [u, v],
+            }
+        });
+        mesh
+    }
+
+    /// A cylinder of `height` with half spheres on both ends, so the whole capsule is
+    /// `height + 2 * radius` tall. `rings` is per half sphere.
+    pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Self {
+        let segments = segments.max(3);
+        let rings = rings.max(1);
+        let rows = rings * 2 + 1;
+        let mut mesh = Self::default();
+        push_surface(&mut mesh, rows, segments, |row, column| {
+            let u = column as f32 / segments as f32;
+            // the top half sphere ends on row `rings`, the bottom one starts on the next
+            let (polar, center) = if row <= rings {
+                (row as f32 / rings as f32 * PI / 2.0, height / 2.0)
+            } else {
+                (
+                    (1.0 + (row - rings - 1) as f32 / rings as f32) * PI / 2.0,
+                    -height / 2.0,
+                )
+            };
+            let normal = around_y(u, polar);
+            MeshVertex {
+                position: [
+                    normal.x * radius,
+                    normal.y * radius + center,
+                    normal.z * radius,
+                ],
+                normal: normal.into(),
+                uv: [u, row as f32 / rows as f32],
+            }
+        });
+        mesh
+    }
+}
+
+impl LineData {
+    /// The outlines of `cells` cells in the xz plane.
+    pub fn grid(cell_size: f32, cells: Vector2<u32>) -> Self {
+        let size = cells.map(|p| p as f32 * cell_size);
+        let mut lines = Self::default();
+
+        let mut push_line = |from: [f32; 2], to: [f32; 2]| {
+            for [x, z] in [from, to] {
+                lines.vertices.push(MeshVertex {
+                    position: [x - size.x / 2.0, 0.0, z - size.y / 2.0],
+                    normal: [0.0, 1.0, 0.0],
+                    uv: [x / size.x.max(f32::EPSILON), z / size.y.max(f32::EPSILON)],
+                });
+            }
+            let first = lines.vertices.len() as u32 - 2;
+            lines.indices.extend([first, first + 1]);
+        };
+
+        for column in 0..=cells.x {
+            let x = column as f32 * cell_size;
+            push_line([x, 0.0], [x, size.y]);
+        }
+        for row in 0..=cells.y {
+            let z = row as f32 * cell_size;
+            push_line([0.0, z], [size.x, z]);
+        }
+
+        lines
+    }
+}
+
+/// The unit vector at `polar` radians from +y, turned around y by the fraction `u` of a full
+/// turn. `u` = 0 points towards +z, 0.25 towards +x.
+fn around_y(u: f32, polar: f32) -> Vector3<f32> {
+    let (azimuth_sin, azimuth_cos) = (u * 2.0 * PI).sin_cos();
+    let (polar_sin, polar_cos) = polar.sin_cos();
+    Vector3::new(azimuth_sin * polar_sin, polar_cos, azimuth_cos * polar_sin)
+}
+
+/// Adds a `(rows + 1) * (columns + 1)` grid of vertices and two triangles per cell.
+/// `vertex(row + 1, _)` has to be "below" `vertex(row, _)` and `vertex(_, column + 1)` to the
+/// right of `vertex(_, column)` when seen from outside. Triangles without an area, like the ones
+/// touching the poles of a sphere, are left out.
+fn push_surface(
+    mesh: &mut MeshData,
+    rows: u32,
+    columns: u32,
+    vertex: impl Fn(u32, u32) -> MeshVertex,
+) {
+    let first = mesh.vertices.len() as u32;
+    for row in 0..=rows {
+        for column in 0..=columns {
+            mesh.vertices.push(vertex(row, column));
+        }
+    }
+
+    let index = |row: u32, column: u32| first + row * (columns + 1) + column;
+    for row in 0..rows {
+        for column in 0..columns {
+            let top_left = index(row, column);
+            let top_right = index(row, column + 1);
+            let bottom_left = index(row + 1, column);
+            let bottom_right = index(row + 1, column + 1);
+
+            for triangle in [
+                [top_left, bottom_left, top_right],
+                [top_right, bottom_left, bottom_right],
+            ] {
+                let [a, b, c] = triangle.map(|p| Vector3::from(mesh.vertices[p as usize].position));
+                let (ab, ac) = (b - a, c - a);
+                // relative to the edge lengths, so it works at any scale
+                let degenerate =
+                    ab.cross(ac).magnitude2() <= 1e-10 * ab.magnitude2() * ac.magnitude2();
+                if !degenerate {
+                    mesh.indices.extend(triangle);
+                }
+            }
+        }
+    }
+}
+
+/// A disc at height `y`, facing up or down.
+fn push_cap(mesh: &mut MeshData, radius: f32, y: f32, segments: u32, facing_up: bool) {
+    let normal = if facing_up {
+        [0.0, 1.0, 0.0]
+    } else {
+        [0.0, -1.0, 0.0]
+    };
+    let center = mesh.vertices.len() as u32;
+    mesh.vertices.push(MeshVertex {
+        position: [0.0, y, 0.0],
+        normal: normal,
+        uv: [0.5, 0.5],
+    });
+
+    for segment in 0..=segments {
+        let around = around_y(segment as f32 / segments as f32, PI / 2.0);
+        mesh.vertices.push(MeshVertex {
+            position: [around.x * radius, y, around.z * radius],
+            normal: normal,
+            uv: [0.5 + around.x / 2.0, 0.5 + around.z / 2.0],
+        });
+    }
+
+    for segment in 0..segments {
+        let (current, next) = (center + 1 + segment, center + 2 + segment);
+        if facing_up {
+            mesh.indices.extend([center, current, next]);
+        } else {
+            mesh.indices.extend([center, next, current]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shapes() -> Vec<(&'static str, MeshData)> {
+        vec![
+            (
+                "plane",
+                MeshData::plane(Vector2::new(2.0, 1.0), Vector2::new(3, 2)),
+            ),
+            ("cuboid", MeshData::cuboid(Vector3::new(1.0, 2.0, 3.0))),
+            ("uv_sphere", MeshData::uv_sphere(1.5, 8, 6)),
+            ("icosphere", MeshData::icosphere(1.5, 2)),
+            ("cylinder", MeshData::cylinder(0.5, 2.0, 8)),
+            ("cone", MeshData::cone(0.5, 2.0, 8)),
+            ("torus", MeshData::torus(1.0, 0.25, 12, 8)),
+            ("capsule", MeshData::capsule(0.5, 1.0, 8, 3)),
+        ]
+    }
+
+    #[test]
+    fn indices_are_in_range() {
+        for (name, mesh) in shapes() {
+            assert_eq!(mesh.indices.len() % 3, 0, "{name}");
+            let count = mesh.vertices.len() as u32;
+            assert!(mesh.indices.iter().all(|p| *p < count), "{name}");
+        }
+    }
+
+    #[test]
+    fn triangles_wind_counter_clockwise_seen_from_outside() {
+        for (name, mesh) in shapes() {
+            for triangle in mesh.indices.chunks_exact(3) {
+                let vertices = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+                let [a, b, c] = vertices.map(|p| Vector3::from(p.position));
+                let outwards = vertices.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, p| {
+                    sum + Vector3::from(p.normal)
+                });
+                assert!(
+                    (b - a).cross(c - a).dot(outwards) > 0.0,
+                    "{name}: {triangle:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn normals_are_unit_length() {
+        for (name, mesh) in shapes() {
+            for vertex in &mesh.vertices {
+                let length = Vector3::from(vertex.normal).magnitude();
+                assert!((length - 1.0).abs() < 1e-5, "{name}: {vertex:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn vertex_counts_follow_the_segments() {
+        assert_eq!(MeshData::uv_sphere(1.0, 8, 4).vertices.len(), 9 * 5);
+        assert_eq!(MeshData::uv_sphere(1.0, 16, 8).vertices.len(), 17 * 9);
+        // the side, then a center and a ring for each cap
+        assert_eq!(
+            MeshData::cylinder(1.0, 1.0, 3).vertices.len(),
+            2 * 4 + 2 * 5
+        );
+        assert_eq!(
+            MeshData::cylinder(1.0, 1.0, 8).vertices.len(),
+            2 * 9 + 2 * 10
+        );
+        assert_eq!(MeshData::icosphere(1.0, 0).vertices.len(), 12);
+        assert_eq!(MeshData::icosphere(1.0, 1).vertices.len(), 42);
+        assert_eq!(MeshData::torus(1.0, 0.5, 6, 4).vertices.len(), 7 * 5);
+    }
+
+    #[test]
+    fn grid_has_a_line_per_cell_edge() {
+        let grid = LineData::grid(0.5, Vector2::new(3, 2));
+
+        assert_eq!(grid.indices.len(), 2 * (4 + 3));
+        let count = grid.vertices.len() as u32;
+        assert!(grid.indices.iter().all(|p| *p < count));
+
+        let bounds = grid.bounds().unwrap();
+        assert_eq!(bounds.min.x, -0.75);
+        assert_eq!(bounds.max.z, 0.5);
+    }
+}
@@ -5,7 +5,7 @@ use vulkano::{
     pipeline::{
         graphics::{
             color_blend::ColorBlendState,
-            depth_stencil::DepthStencilState,
+            depth_stencil::{CompareOp, DepthState, DepthStencilState},
             discard_rectangle::DiscardRectangleState,
             input_assembly::InputAssemblyState,
             multisample::MultisampleState,
@@ -22,11 +22,14 @@ use vulkano::{
     shader::ShaderModule,
 };
 
-use super::Graphics;
+use super::{drawable::RenderLayer, error::GraphicsError, Graphics};
 
+#[derive(Clone)]
 pub struct PipelineBuilder {
     pub subpass: Subpass,
     pub vertex_buffer_description: Option<VertexBufferDescription>,
+    /// Bound as the second vertex buffer binding.
+    pub instance_buffer_description: Option<VertexBufferDescription>,
     pub input_assembly_state: InputAssemblyState,
     pub vertex_shader: Option<Arc<ShaderModule>>,
     pub fragment_shader: Option<Arc<ShaderModule>>,
@@ -47,6 +50,7 @@ impl PipelineBuilder {
         Self {
             subpass: Subpass::from(gfx.get_main_render_pass(), 0).unwrap(),
             vertex_buffer_description: None,
+            instance_buffer_description: None,
             input_assembly_state: InputAssemblyState::new(),
             vertex_shader: None,
             fragment_shader: None,
@@ -68,48 +72,84 @@ impl PipelineBuilder {
         }
     }
 
-    pub fn build(self, device: Arc<Device>) -> (Arc<GraphicsPipeline>, Arc<PipelineLayout>) {
+    /// Adjusts blending and depth testing for drawables on `layer`.
+    /// Opaque is what bindables set up, so it's left as is.
+    pub fn apply_layer(&mut self, layer: RenderLayer) {
+        match layer {
+            RenderLayer::Opaque => {}
+            RenderLayer::Transparent => {
+                self.color_blend_state = ColorBlendState::new(1).blend_alpha();
+                // tested against opaque geometry, but doesn't hide what's drawn behind it later
+                self.depth_stencil_state = DepthStencilState {
+                    depth: Some(DepthState {
+                        enable_dynamic: false,
+                        write_enable: StateMode::Fixed(false),
+                        compare_op: StateMode::Fixed(CompareOp::Less),
+                    }),
+                    ..DepthStencilState::disabled()
+                };
+            }
+            RenderLayer::Ui => {
+                self.color_blend_state = ColorBlendState::new(1).blend_alpha();
+                self.depth_stencil_state = DepthStencilState::disabled();
+            }
+        }
+    }
+
+    pub fn build(
+        self,
+        device: Arc<Device>,
+    ) -> Result<(Arc<GraphicsPipeline>, Arc<PipelineLayout>), GraphicsError> {
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineLayoutCreateInfo {
+                set_layouts: self.desriptor_set_layouts.clone(),
+                push_constant_ranges: self.push_constant_ranges.clone(),
+                ..Default::default()
+            },
+        )
+        .map_err(GraphicsError::vulkan)?;
+
+        Ok((self.build_with_layout(device, layout.clone())?, layout))
+    }
+
+    /// Pipelines sharing a layout can use the same bound descriptor sets and push constants.
+    pub fn build_with_layout(
+        self,
+        device: Arc<Device>,
+        layout: Arc<PipelineLayout>,
+    ) -> Result<Arc<GraphicsPipeline>, GraphicsError> {
         let vertex_shader_entry = self
             .vertex_shader
             .as_ref()
-            .expect("No vertex shader supplied.")
-            .entry_point("main")
-            .unwrap();
+            .and_then(|shader| shader.entry_point("main"))
+            .ok_or(GraphicsError::IncompletePipeline("vertex shader"))?;
 
         let fragment_shader_entry = self
             .fragment_shader
             .as_ref()
-            .expect("No fragment shader supplied.")
-            .entry_point("main")
-            .unwrap();
+            .and_then(|shader| shader.entry_point("main"))
+            .ok_or(GraphicsError::IncompletePipeline("fragment shader"))?;
 
-        let layout = PipelineLayout::new(
-            device.clone(),
-            PipelineLayoutCreateInfo {
-                set_layouts: self.desriptor_set_layouts,
-                push_constant_ranges: self.push_constant_ranges,
-                ..Default::default()
-            },
-        )
-        .unwrap();
+        let mut vertex_input_descriptions = vec![self
+            .vertex_buffer_description
+            .ok_or(GraphicsError::IncompletePipeline("vertex buffer"))?];
+        vertex_input_descriptions.extend(self.instance_buffer_description);
 
-        (
-            GraphicsPipeline::start()
-                .render_pass(PipelineRenderPassType::BeginRenderPass(self.subpass))
-                .vertex_input_state(self.vertex_buffer_description.unwrap())
-                .input_assembly_state(self.input_assembly_state)
-                .vertex_shader(vertex_shader_entry, ())
-                .fragment_shader(fragment_shader_entry, ())
-                .viewport_state(self.viewport_state)
-                .color_blend_state(self.color_blend_state)
-                .rasterization_state(self.rasterization_state)
-                .depth_stencil_state(self.depth_stencil_state)
-                .discard_rectangle_state(self.discard_rectangle_state)
-                .multisample_state(self.multisample_state)
-                .tessellation_state(self.tessellation_state)
-                .with_pipeline_layout(device.clone(), layout.clone())
-                .expect("Failed to create pipeline!"),
-            layout,
-        )
+        GraphicsPipeline::start()
+            .render_pass(PipelineRenderPassType::BeginRenderPass(self.subpass))
+            .vertex_input_state(vertex_input_descriptions)
+            .input_assembly_state(self.input_assembly_state)
+            .vertex_shader(vertex_shader_entry, ())
+            .fragment_shader(fragment_shader_entry, ())
+            .viewport_state(self.viewport_state)
+            .color_blend_state(self.color_blend_state)
+            .rasterization_state(self.rasterization_state)
+            .depth_stencil_state(self.depth_stencil_state)
+            .discard_rectangle_state(self.discard_rectangle_state)
+            .multisample_state(self.multisample_state)
+            .tessellation_state(self.tessellation_state)
+            .with_pipeline_layout(device, layout)
+            .map_err(GraphicsError::vulkan)
     }
 }
@@ -0,0 +1,218 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use vulkano::{
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder,
+        CommandBufferInheritanceInfo, CommandBufferInheritanceRenderPassInfo,
+        CommandBufferInheritanceRenderPassType, CommandBufferUsage, SecondaryAutoCommandBuffer,
+    },
+    pipeline::{graphics::viewport::Viewport, GraphicsPipeline},
+    render_pass::{Framebuffer, Subpass},
+};
+
+use super::{
+    bindable::BindContext,
+    drawable::{Drawable, DrawableSharedPart, GenericDrawable, RenderLayer},
+    error::GraphicsError,
+};
+
+/// Below this many drawables per thread, spawning another thread costs more than it saves.
+const MIN_DRAWABLES_PER_THREAD: usize = 64;
+
+/// Counts from the last time the command buffers were recorded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Drawables that were recorded.
+    pub drawables: u32,
+    /// Drawables left out because they were outside the camera's frustum.
+    pub culled: u32,
+    pub draw_calls: u32,
+    pub instances: u32,
+    pub pipeline_binds: u32,
+    /// Times the shared bindables of a `DrawableSharedPart` were bound.
+    pub shared_binds: u32,
+    pub secondary_command_buffers: u32,
+}
+
+impl std::ops::AddAssign for RenderStats {
+    fn add_assign(&mut self, other: Self) {
+        self.drawables += other.drawables;
+        self.culled += other.culled;
+        self.draw_calls += other.draw_calls;
+        self.instances += other.instances;
+        self.pipeline_binds += other.pipeline_binds;
+        self.shared_binds += other.shared_binds;
+        self.secondary_command_buffers += other.secondary_command_buffers;
+    }
+}
+
+/// A registered drawable with the pipeline for its layer.
+pub(super) struct DrawItem {
+    pub drawable: Arc<GenericDrawable>,
+    pub pipeline: Arc<GraphicsPipeline>,
+    pub layer: RenderLayer,
+    pub order: i32,
+    pub depth: f32,
+}
+
+impl DrawItem {
+    fn state_key(&self) -> (usize, usize) {
+        (
+            Arc::as_ptr(&self.pipeline) as usize,
+            Arc::as_ptr(self.drawable.get_shared_part()) as usize,
+        )
+    }
+
+    /// Layers and orders come first. Opaque drawables are grouped by pipeline and shared part
+    /// and drawn front to back within a group, transparent ones are drawn back to front.
+    /// Ties keep their registration order.
+    fn draw_order(&self, other: &Self) -> Ordering {
+        let by_layer = (self.layer, self.order).cmp(&(other.layer, other.order));
+        let by_depth = self.depth.total_cmp(&other.depth);
+
+        by_layer.then_with(|| match self.layer {
+            RenderLayer::Opaque => self.state_key().cmp(&other.state_key()).then(by_depth),
+            RenderLayer::Transparent => by_depth.reverse(),
+            RenderLayer::Ui => Ordering::Equal,
+        })
+    }
+}
+
+/// Everything needed to record draws into secondary command buffers from worker threads.
+pub(super) struct DrawRecorder<'a> {
+    pub allocator: &'a StandardCommandBufferAllocator,
+    pub queue_family_index: u32,
+    pub subpass: Subpass,
+    pub framebuffer: Arc<Framebuffer>,
+    pub viewport: Viewport,
+    pub ctx: BindContext,
+}
+
+impl<'a> DrawRecorder<'a> {
+    /// Sorts `drawables` into draw order, splits them into contiguous chunks and records each
+    /// one on its own thread. The returned command buffers have to be executed in order.
+    /// `max_threads` of 0 uses the available parallelism.
+    pub fn record(
+        &self,
+        mut drawables: Vec<DrawItem>,
+        max_threads: usize,
+    ) -> Result<(Vec<SecondaryAutoCommandBuffer>, RenderStats), GraphicsError> {
+        if drawables.is_empty() {
+            return Ok((Vec::new(), RenderStats::default()));
+        }
+
+        drawables.sort_by(DrawItem::draw_order);
+
+        let max_threads = match max_threads {
+            0 => std::thread::available_parallelism().map_or(1, |p| p.get()),
+            count => count,
+        };
+        let thread_count = max_threads
+            .min(drawables.len().div_ceil(MIN_DRAWABLES_PER_THREAD))
+            .max(1);
+
+        let results = if thread_count == 1 {
+            vec![self.record_chunk(&drawables)]
+        } else {
+            let chunk_size = drawables.len().div_ceil(thread_count);
+
+            std::thread::scope(|scope| {
+                let workers: Vec<_> = drawables
+                    .chunks(chunk_size)
+                    .map(|chunk| scope.spawn(move || self.record_chunk(chunk)))
+                    .collect();
+
+                workers
+                    .into_iter()
+                    .map(|worker| {
+                        worker.join().unwrap_or_else(|_| {
+                            Err(GraphicsError::vulkan("a command recording thread panicked"))
+                        })
+                    })
+                    .collect()
+            })
+        };
+
+        let mut command_buffers = Vec::with_capacity(results.len());
+        let mut stats = RenderStats::default();
+        for result in results {
+            let (command_buffer, chunk_stats) = result?;
+            command_buffers.push(command_buffer);
+            stats += chunk_stats;
+        }
+
+        Ok((command_buffers, stats))
+    }
+
+    /// Shared state is only bound when it differs from the previous drawable's.
+    fn record_chunk(
+        &self,
+        items: &[DrawItem],
+    ) -> Result<(SecondaryAutoCommandBuffer, RenderStats), GraphicsError> {
+        let mut builder = AutoCommandBufferBuilder::secondary(
+            self.allocator,
+            self.queue_family_index,
+            CommandBufferUsage::SimultaneousUse,
+            CommandBufferInheritanceInfo {
+                render_pass: Some(CommandBufferInheritanceRenderPassType::BeginRenderPass(
+                    CommandBufferInheritanceRenderPassInfo {
+                        subpass: self.subpass.clone(),
+                        framebuffer: Some(self.framebuffer.clone()),
+                    },
+                )),
+                ..Default::default()
+            },
+        )
+        .map_err(GraphicsError::vulkan)?;
+
+        // dynamic state isn't inherited from the primary command buffer
+        builder.set_viewport(0, [self.viewport.clone()]);
+
+        let mut stats = RenderStats {
+            secondary_command_buffers: 1,
+            ..Default::default()
+        };
+        let mut bound_pipeline: Option<Arc<GraphicsPipeline>> = None;
+        let mut bound_shared_part: Option<&Arc<DrawableSharedPart>> = None;
+
+        for item in items {
+            let drawable = &item.drawable;
+            stats.drawables += 1;
+
+            let instance_count = drawable.get_instance_count();
+            let index_count = drawable.get_index_count();
+            if instance_count == 0 || index_count == 0 {
+                continue;
+            }
+
+            if bound_pipeline.as_ref() != Some(&item.pipeline) {
+                builder.bind_pipeline_graphics(item.pipeline.clone());
+                bound_pipeline = Some(item.pipeline.clone());
+                // a new pipeline may have an incompatible layout
+                bound_shared_part = None;
+                stats.pipeline_binds += 1;
+            }
+
+            let shared_part = drawable.get_shared_part();
+            if !bound_shared_part.is_some_and(|p| Arc::ptr_eq(p, shared_part)) {
+                for bindable in drawable.get_shared_bindables() {
+                    bindable.bind(&self.ctx, &mut builder, drawable.get_pipeline_layout());
+                }
+                bound_shared_part = Some(shared_part);
+                stats.shared_binds += 1;
+            }
+
+            for bindable in drawable.get_bindables() {
+                bindable.bind(&self.ctx, &mut builder, drawable.get_pipeline_layout());
+            }
+
+            builder
+                .draw_indexed(index_count, instance_count, 0, 0, 0)
+                .map_err(GraphicsError::vulkan)?;
+            stats.draw_calls += 1;
+            stats.instances += instance_count;
+        }
+
+        Ok((builder.build().map_err(GraphicsError::vulkan)?, stats))
+    }
+}
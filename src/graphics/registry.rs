@@ -0,0 +1,400 @@
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+use cgmath::{Matrix4, SquareMatrix};
+
+use super::{
+    bindable::DirtyFlag,
+    bounds::Frustum,
+    camera::Camera,
+    drawable::{GenericDrawable, RenderLayer},
+    error::GraphicsError,
+    recording::DrawItem,
+};
+
+/// Refers to a registered drawable. Handles of removed drawables never match a drawable that
+/// later reuses their slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DrawableHandle {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot<D> {
+    generation: u32,
+    drawable: Option<D>,
+    layer: RenderLayer,
+    /// Drawables with a lower order are drawn first within their layer.
+    order: i32,
+    /// Used to sort by depth and to place the drawable's bounds when culling. Drawables that
+    /// never had one set are moved by other means, so they aren't culled.
+    transform: Option<Matrix4<f32>>,
+    /// Hidden drawables stay registered but aren't drawn.
+    visible: bool,
+}
+
+impl<D> Slot<D> {
+    fn reset(&mut self, drawable: D) {
+        self.drawable = Some(drawable);
+        self.layer = RenderLayer::Opaque;
+        self.order = 0;
+        self.transform = None;
+        self.visible = true;
+    }
+}
+
+/// Generic over the drawable only so that the arena can be tested without a device.
+struct RegistryState<D = Arc<GenericDrawable>> {
+    slots: Vec<Slot<D>>,
+    free: Vec<u32>,
+    len: usize,
+    /// Bit per `RenderLayer`, layers without their bit set aren't drawn.
+    visible_layers: u8,
+    /// Marked whenever the set of drawn drawables or their order changes.
+    dirty: DirtyFlag,
+}
+
+/// Generational arena of the drawables that are drawn every frame.
+/// Cloning gives another reference to the same registry.
+#[derive(Clone)]
+pub struct DrawableRegistry {
+    state: Arc<Mutex<RegistryState>>,
+}
+
+/// Removes its drawable from the registry when dropped, owned by the `DrawableEntry`.
+pub(super) struct Registration<D = Arc<GenericDrawable>> {
+    handle: DrawableHandle,
+    registry: Weak<Mutex<RegistryState<D>>>,
+}
+
+impl DrawableRegistry {
+    pub fn new(dirty: DirtyFlag) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(RegistryState::new(dirty))),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RegistryState> {
+        // the state is always left consistent, so a poisoned lock is still usable
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn insert(&self, drawable: Arc<GenericDrawable>) -> DrawableHandle {
+        self.lock().insert(drawable)
+    }
+
+    /// Returns `None` if the handle was already removed.
+    pub fn remove(&self, handle: DrawableHandle) -> Option<Arc<GenericDrawable>> {
+        self.lock().remove(handle)
+    }
+
+    pub fn get(&self, handle: DrawableHandle) -> Option<Arc<GenericDrawable>> {
+        self.lock().slot_mut(handle)?.drawable.clone()
+    }
+
+    /// New drawables start out on the opaque layer with order 0.
+    /// Returns false if the handle was already removed.
+    pub fn set_layer(&self, handle: DrawableHandle, layer: RenderLayer, order: i32) -> bool {
+        let mut state = self.lock();
+        match state.slot_mut(handle) {
+            Some(slot) => {
+                slot.layer = layer;
+                slot.order = order;
+                state.dirty.mark();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Hiding a drawable keeps its slot and settings, so showing it again is cheap.
+    /// Returns false if the handle was already removed.
+    pub fn set_visible(&self, handle: DrawableHandle, visible: bool) -> bool {
+        let mut state = self.lock();
+        match state.slot_mut(handle) {
+            Some(slot) => {
+                if slot.visible != visible {
+                    slot.visible = visible;
+                    state.dirty.mark();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `None` if the handle was already removed.
+    pub fn is_visible(&self, handle: DrawableHandle) -> Option<bool> {
+        Some(self.lock().slot_mut(handle)?.visible)
+    }
+
+    /// Hides or shows every drawable on `layer`, regardless of their own visibility.
+    pub fn set_layer_visible(&self, layer: RenderLayer, visible: bool) {
+        let mut state = self.lock();
+        let mask = if visible {
+            state.visible_layers | layer_bit(layer)
+        } else {
+            state.visible_layers & !layer_bit(layer)
+        };
+        if mask != state.visible_layers {
+            state.visible_layers = mask;
+            state.dirty.mark();
+        }
+    }
+
+    pub fn is_layer_visible(&self, layer: RenderLayer) -> bool {
+        self.lock().visible_layers & layer_bit(layer) != 0
+    }
+
+    /// The model transform of the drawable, the view space depth of its translation decides
+    /// the order within the opaque and transparent layers. Only drawables with a transform are
+    /// culled, so it has to match the one the drawable renders with.
+    /// Returns false if the handle was already removed.
+    pub fn set_transform(&self, handle: DrawableHandle, transform: Matrix4<f32>) -> bool {
+        let mut state = self.lock();
+        match state.slot_mut(handle) {
+            Some(slot) => {
+                slot.transform = Some(transform);
+                // the draw order may have changed
+                state.dirty.mark();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn contains(&self, handle: DrawableHandle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All registered drawables in slot order.
+    pub fn drawables(&self) -> Vec<Arc<GenericDrawable>> {
+        self.lock()
+            .slots
+            .iter()
+            .filter_map(|slot| slot.drawable.clone())
+            .collect()
+    }
+
+    /// Everything the recorder needs to sort and draw the visible drawables. Each drawable is
+    /// culled and sorted with the camera of `cameras` whose uniform it binds, drawables without
+    /// one use `fallback` for their depth and are never culled. Returns the culled count as well.
+    pub(super) fn draw_items(
+        &self,
+        cameras: &[Arc<Camera>],
+        fallback: &Camera,
+    ) -> Result<(Vec<DrawItem>, u32), GraphicsError> {
+        let views: Vec<(&Camera, Matrix4<f32>, Frustum)> = cameras
+            .iter()
+            .map(|camera| {
+                let frustum = Frustum::from_view_projection(&camera.get_view_projection());
+                (camera.as_ref(), camera.get_view(), frustum)
+            })
+            .collect();
+        let fallback_view = fallback.get_view();
+
+        let state = self.lock();
+        let mut culled = 0;
+        let mut items = Vec::new();
+        for slot in &state.slots {
+            if !slot.visible || state.visible_layers & layer_bit(slot.layer) == 0 {
+                continue;
+            }
+            let Some(drawable) = slot.drawable.clone() else {
+                continue;
+            };
+
+            let camera = views
+                .iter()
+                .find(|(camera, _, _)| camera.is_bound_by(drawable.as_ref()));
+            let transform = slot.transform.unwrap_or_else(Matrix4::identity);
+
+            let outside = match (camera, slot.transform, drawable.get_bounds()) {
+                (Some((_, _, frustum)), Some(transform), Some(bounds)) => {
+                    !frustum.intersects(&bounds.transformed(&transform))
+                }
+                _ => false,
+            };
+            if outside {
+                culled += 1;
+                continue;
+            }
+
+            let view = camera.map_or(fallback_view, |(_, view, _)| *view);
+            items.push(DrawItem {
+                pipeline: drawable.get_shared_part().get_layer_pipeline(slot.layer)?,
+                drawable: drawable,
+                layer: slot.layer,
+                order: slot.order,
+                // view space looks down -z
+                depth: -(view * transform.w).z,
+            });
+        }
+
+        Ok((items, culled))
+    }
+
+    pub(super) fn register(&self, drawable: Arc<GenericDrawable>) -> Registration {
+        Registration {
+            handle: self.insert(drawable),
+            registry: Arc::downgrade(&self.state),
+        }
+    }
+}
+
+fn layer_bit(layer: RenderLayer) -> u8 {
+    1 << layer as u8
+}
+
+impl<D> RegistryState<D> {
+    fn new(dirty: DirtyFlag) -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            visible_layers: u8::MAX,
+            dirty: dirty,
+        }
+    }
+
+    fn insert(&mut self, drawable: D) -> DrawableHandle {
+        self.len += 1;
+        self.dirty.mark();
+
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.reset(drawable);
+                DrawableHandle {
+                    index: index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                let mut slot = Slot {
+                    generation: 0,
+                    drawable: None,
+                    layer: RenderLayer::Opaque,
+                    order: 0,
+                    transform: None,
+                    visible: true,
+                };
+                slot.reset(drawable);
+                self.slots.push(slot);
+                DrawableHandle {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    fn slot_mut(&mut self, handle: DrawableHandle) -> Option<&mut Slot<D>> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+    }
+
+    fn remove(&mut self, handle: DrawableHandle) -> Option<D> {
+        let slot = self.slot_mut(handle)?;
+        let drawable = slot.drawable.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        self.len -= 1;
+        self.dirty.mark();
+
+        Some(drawable)
+    }
+}
+
+impl<D> Registration<D> {
+    pub fn get_handle(&self) -> DrawableHandle {
+        self.handle
+    }
+}
+
+impl<D> Drop for Registration<D> {
+    fn drop(&mut self) {
+        if let Some(registry) = self.registry.upgrade() {
+            let mut state = registry.lock().unwrap_or_else(|e| e.into_inner());
+            // the drawable is dropped after the lock is released
+            let _drawable = state.remove(self.handle);
+            drop(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arena() -> Arc<Mutex<RegistryState<u32>>> {
+        Arc::new(Mutex::new(RegistryState::new(DirtyFlag::default())))
+    }
+
+    #[test]
+    fn stale_handle_misses_reused_slot() {
+        let arena = arena();
+        let mut state = arena.lock().unwrap();
+        let first = state.insert(1);
+        assert_eq!(state.remove(first), Some(1));
+
+        let second = state.insert(2);
+        assert_eq!(second.index, first.index);
+        assert_ne!(second.generation, first.generation);
+
+        assert!(state.slot_mut(first).is_none());
+        assert_eq!(state.remove(first), None);
+        assert_eq!(state.slot_mut(second).unwrap().drawable, Some(2));
+        assert_eq!(state.len, 1);
+    }
+
+    #[test]
+    fn removing_twice_only_frees_once() {
+        let arena = arena();
+        let mut state = arena.lock().unwrap();
+        let handle = state.insert(1);
+        state.remove(handle);
+        state.remove(handle);
+
+        assert_eq!(state.free, [handle.index]);
+        assert_eq!(state.len, 0);
+    }
+
+    #[test]
+    fn dropping_registration_unregisters() {
+        let arena = arena();
+        let handle = arena.lock().unwrap().insert(1);
+        let registration = Registration {
+            handle: handle,
+            registry: Arc::downgrade(&arena),
+        };
+        let dirty = arena.lock().unwrap().dirty.clone();
+        dirty.take();
+
+        drop(registration);
+
+        let mut state = arena.lock().unwrap();
+        assert!(state.slot_mut(handle).is_none());
+        assert_eq!(state.len, 0);
+        assert!(dirty.take());
+    }
+
+    #[test]
+    fn registration_outliving_registry_is_harmless() {
+        let arena = arena();
+        let registration = Registration {
+            handle: arena.lock().unwrap().insert(1),
+            registry: Arc::downgrade(&arena),
+        };
+        drop(arena);
+        drop(registration);
+    }
+}
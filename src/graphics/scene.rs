@@ -0,0 +1,445 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use cgmath::{Matrix4, One, Quaternion, SquareMatrix, Vector3};
+
+use super::{
+    bindable::FrameUpdate,
+    registry::{DrawableHandle, DrawableRegistry},
+    Graphics,
+};
+
+/// Local transform of a node, applied as scale, then rotation, then translation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            translation: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn from_translation(translation: Vector3<f32>) -> Self {
+        Self {
+            translation: translation,
+            ..Self::identity()
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// Refers to a node of a `SceneGraph`. Ids of removed nodes never match a node that later
+/// reuses their slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+/// Called with the node's world matrix whenever it changes.
+pub type TransformCallback = Box<dyn Fn(Matrix4<f32>) + Send + Sync>;
+
+enum Attachment {
+    Callback(TransformCallback),
+    /// Keeps the transform the registry sorts by in sync.
+    Drawable(DrawableHandle),
+}
+
+struct Node {
+    generation: u32,
+    alive: bool,
+    parent: Option<u32>,
+    children: Vec<u32>,
+    local: Transform,
+    world: Matrix4<f32>,
+    /// Set when the local transform or the parent changed since the last update.
+    changed: bool,
+    attachments: Vec<Attachment>,
+}
+
+struct SceneState {
+    nodes: Vec<Node>,
+    free: Vec<u32>,
+}
+
+/// Nodes with local transforms arranged in a hierarchy. World matrices are propagated once per
+/// frame and handed to whatever is attached to the nodes, so only nodes that actually moved
+/// cause push constants or uniforms to be written.
+pub struct SceneGraph {
+    registry: DrawableRegistry,
+    state: Mutex<SceneState>,
+}
+
+impl SceneGraph {
+    /// Registers the graph for per frame updates.
+    pub fn new(gfx: &Graphics) -> Arc<Self> {
+        let scene = Arc::new(Self {
+            registry: gfx.get_registry().clone(),
+            state: Mutex::new(SceneState {
+                nodes: Vec::new(),
+                free: Vec::new(),
+            }),
+        });
+
+        gfx.register_frame_update(Arc::downgrade(&scene) as _);
+
+        scene
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SceneState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A node without a living parent is a root.
+    pub fn create_node(&self, parent: Option<NodeId>, transform: Transform) -> NodeId {
+        let mut state = self.lock();
+        let parent = parent.and_then(|p| state.index_of(p));
+
+        let node = Node {
+            generation: 0,
+            alive: true,
+            parent: parent,
+            children: Vec::new(),
+            local: transform,
+            world: Matrix4::identity(),
+            changed: true,
+            attachments: Vec::new(),
+        };
+
+        let id = match state.free.pop() {
+            Some(index) => {
+                let slot = &mut state.nodes[index as usize];
+                let generation = slot.generation;
+                *slot = Node {
+                    generation: generation,
+                    ..node
+                };
+                NodeId {
+                    index: index,
+                    generation: generation,
+                }
+            }
+            None => {
+                state.nodes.push(node);
+                NodeId {
+                    index: state.nodes.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        };
+
+        if let Some(parent) = parent {
+            state.nodes[parent as usize].children.push(id.index);
+        }
+
+        id
+    }
+
+    /// Removes the node together with all of its descendants and their attachments.
+    pub fn remove_node(&self, id: NodeId) {
+        let mut state = self.lock();
+        let Some(index) = state.index_of(id) else {
+            return;
+        };
+
+        state.detach_from_parent(index);
+
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            let node = &mut state.nodes[index as usize];
+            stack.append(&mut node.children);
+            node.alive = false;
+            node.parent = None;
+            node.attachments.clear();
+            node.generation = node.generation.wrapping_add(1);
+            state.free.push(index);
+        }
+    }
+
+    /// Moving a node under one of its own descendants is ignored.
+    pub fn set_parent(&self, id: NodeId, parent: Option<NodeId>) {
+        let mut state = self.lock();
+        let Some(index) = state.index_of(id) else {
+            return;
+        };
+        let parent = parent.and_then(|p| state.index_of(p));
+
+        // walk up from the new parent to make sure no cycle is created
+        let mut ancestor = parent;
+        while let Some(current) = ancestor {
+            if current == index {
+                log::warn!("Tried to parent a scene node to its own descendant.");
+                return;
+            }
+            ancestor = state.nodes[current as usize].parent;
+        }
+
+        state.detach_from_parent(index);
+        if let Some(parent) = parent {
+            state.nodes[parent as usize].children.push(index);
+        }
+        let node = &mut state.nodes[index as usize];
+        node.parent = parent;
+        node.changed = true;
+    }
+
+    pub fn get_transform(&self, id: NodeId) -> Option<Transform> {
+        let state = self.lock();
+        Some(state.nodes[state.index_of(id)? as usize].local)
+    }
+
+    pub fn set_transform(&self, id: NodeId, transform: Transform) {
+        self.update_transform(id, |local| *local = transform);
+    }
+
+    pub fn update_transform(&self, id: NodeId, update: impl FnOnce(&mut Transform)) {
+        let mut state = self.lock();
+        if let Some(index) = state.index_of(id) {
+            let node = &mut state.nodes[index as usize];
+            let previous = node.local;
+            update(&mut node.local);
+            node.changed |= node.local != previous;
+        }
+    }
+
+    /// The world matrix as of the last update.
+    pub fn get_world_matrix(&self, id: NodeId) -> Option<Matrix4<f32>> {
+        let state = self.lock();
+        Some(state.nodes[state.index_of(id)? as usize].world)
+    }
+
+    /// `callback` is called on the next update and whenever the world matrix changes after that.
+    /// It runs while the graph is locked, so it must not call back into the graph.
+    pub fn attach(&self, id: NodeId, callback: impl Fn(Matrix4<f32>) + Send + Sync + 'static) {
+        self.add_attachment(id, Attachment::Callback(Box::new(callback)));
+    }
+
+    /// Keeps the drawable's registry transform, which decides its draw order, in sync with the
+    /// node. Its push constants or uniforms still have to be attached with `attach`.
+    pub fn attach_drawable(&self, id: NodeId, handle: DrawableHandle) {
+        self.add_attachment(id, Attachment::Drawable(handle));
+    }
+
+    fn add_attachment(&self, id: NodeId, attachment: Attachment) {
+        let mut state = self.lock();
+        if let Some(index) = state.index_of(id) {
+            let node = &mut state.nodes[index as usize];
+            node.attachments.push(attachment);
+            // make sure the new attachment receives the current matrix
+            node.changed = true;
+        }
+    }
+
+    /// Propagates world matrices from the roots down and notifies the attachments of every node
+    /// whose world matrix changed.
+    pub fn update(&self) {
+        let mut state = self.lock();
+
+        let mut stack: Vec<(u32, Matrix4<f32>, bool)> = state
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.alive && node.parent.is_none())
+            .map(|(index, _)| (index as u32, Matrix4::identity(), false))
+            .collect();
+
+        while let Some((index, parent_world, parent_changed)) = stack.pop() {
+            let node = &mut state.nodes[index as usize];
+            let changed = node.changed || parent_changed;
+
+            if changed {
+                node.world = parent_world * node.local.matrix();
+                node.changed = false;
+
+                for attachment in &node.attachments {
+                    match attachment {
+                        Attachment::Callback(callback) => callback(node.world),
+                        Attachment::Drawable(handle) => {
+                            self.registry.set_transform(*handle, node.world);
+                        }
+                    }
+                }
+            }
+
+            let world = node.world;
+            stack.extend(node.children.iter().map(|child| (*child, world, changed)));
+        }
+    }
+}
+
+impl SceneState {
+    fn index_of(&self, id: NodeId) -> Option<u32> {
+        let node = self.nodes.get(id.index as usize)?;
+        (node.alive && node.generation == id.generation).then_some(id.index)
+    }
+
+    fn detach_from_parent(&mut self, index: u32) {
+        if let Some(parent) = self.nodes[index as usize].parent {
+            self.nodes[parent as usize].children.retain(|p| *p != index);
+        }
+    }
+}
+
+impl FrameUpdate for SceneGraph {
+    fn update(&self, _in_flight_index: usize) {
+        SceneGraph::update(self);
+    }
+    fn runs_early(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::graphics::bindable::DirtyFlag;
+
+    fn scene() -> SceneGraph {
+        SceneGraph {
+            registry: DrawableRegistry::new(DirtyFlag::default()),
+            state: Mutex::new(SceneState {
+                nodes: Vec::new(),
+                free: Vec::new(),
+            }),
+        }
+    }
+
+    fn translation(x: f32, y: f32, z: f32) -> Transform {
+        Transform::from_translation(Vector3::new(x, y, z))
+    }
+
+    #[test]
+    fn child_world_includes_parent() {
+        let scene = scene();
+        let parent = scene.create_node(
+            None,
+            Transform {
+                scale: Vector3::new(2.0, 2.0, 2.0),
+                ..translation(1.0, 0.0, 0.0)
+            },
+        );
+        let child = scene.create_node(Some(parent), translation(0.0, 1.0, 0.0));
+        scene.update();
+
+        let world = scene.get_world_matrix(child).unwrap();
+        assert_eq!(world.w.truncate(), Vector3::new(1.0, 2.0, 0.0));
+        assert_eq!(
+            world,
+            scene.get_world_matrix(parent).unwrap() * translation(0.0, 1.0, 0.0).matrix()
+        );
+    }
+
+    #[test]
+    fn moving_parent_moves_children_on_update() {
+        let scene = scene();
+        let parent = scene.create_node(None, translation(1.0, 0.0, 0.0));
+        let child = scene.create_node(Some(parent), translation(0.0, 1.0, 0.0));
+        let grandchild = scene.create_node(Some(child), translation(0.0, 0.0, 1.0));
+        scene.update();
+
+        scene.set_transform(parent, translation(5.0, 0.0, 0.0));
+        assert_eq!(
+            scene.get_world_matrix(grandchild).unwrap().w.truncate(),
+            Vector3::new(1.0, 1.0, 1.0)
+        );
+        scene.update();
+        assert_eq!(
+            scene.get_world_matrix(grandchild).unwrap().w.truncate(),
+            Vector3::new(5.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn only_changed_nodes_notify_attachments() {
+        let scene = scene();
+        let parent = scene.create_node(None, translation(1.0, 0.0, 0.0));
+        let child = scene.create_node(Some(parent), Transform::identity());
+        let sibling = scene.create_node(None, Transform::identity());
+
+        let calls = Arc::new(AtomicU32::new(0));
+        for node in [child, sibling] {
+            let calls = calls.clone();
+            scene.attach(node, move |_| {
+                calls.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+
+        scene.update();
+        assert_eq!(calls.swap(0, Ordering::Relaxed), 2);
+
+        scene.update();
+        assert_eq!(calls.swap(0, Ordering::Relaxed), 0);
+
+        // the child follows its parent, the sibling isn't touched
+        scene.update_transform(parent, |transform| transform.translation.x = 2.0);
+        scene.update();
+        assert_eq!(calls.swap(0, Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn reparenting_takes_the_new_parent_into_account() {
+        let scene = scene();
+        let first = scene.create_node(None, translation(1.0, 0.0, 0.0));
+        let second = scene.create_node(None, translation(0.0, 3.0, 0.0));
+        let child = scene.create_node(Some(first), Transform::identity());
+        scene.update();
+
+        scene.set_parent(child, Some(second));
+        scene.update();
+        assert_eq!(
+            scene.get_world_matrix(child).unwrap().w.truncate(),
+            Vector3::new(0.0, 3.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn parenting_to_a_descendant_is_ignored() {
+        let scene = scene();
+        let parent = scene.create_node(None, translation(1.0, 0.0, 0.0));
+        let child = scene.create_node(Some(parent), translation(0.0, 1.0, 0.0));
+
+        scene.set_parent(parent, Some(child));
+        scene.update();
+        assert_eq!(
+            scene.get_world_matrix(child).unwrap().w.truncate(),
+            Vector3::new(1.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn removing_a_node_removes_its_descendants() {
+        let scene = scene();
+        let parent = scene.create_node(None, Transform::identity());
+        let child = scene.create_node(Some(parent), Transform::identity());
+
+        scene.remove_node(parent);
+        assert!(scene.get_transform(parent).is_none());
+        assert!(scene.get_transform(child).is_none());
+
+        // the reused slot doesn't answer to the old id
+        let reused = scene.create_node(None, Transform::identity());
+        assert!(reused.index == parent.index || reused.index == child.index);
+        assert!(scene.get_transform(reused).is_some());
+        assert!(scene.get_transform(parent).is_none());
+        assert!(scene.get_transform(child).is_none());
+    }
+}
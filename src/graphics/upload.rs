@@ -0,0 +1,373 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use vulkano::{
+    buffer::Buffer,
+    command_buffer::{
+        allocator::{
+            CommandBufferAllocator, CommandBufferBuilderAlloc, StandardCommandBufferAlloc,
+            StandardCommandBufferAllocator,
+        },
+        sys::{CommandBufferBeginInfo, UnsafeCommandBuffer, UnsafeCommandBufferBuilder},
+        AutoCommandBufferBuilder, CommandBufferExecFuture, CommandBufferLevel, CommandBufferUsage,
+        PrimaryAutoCommandBuffer, PrimaryCommandBufferAbstract,
+    },
+    device::{DeviceOwned, Queue},
+    image::{sys::Image, ImageLayout},
+    sync::{
+        fence::Fence,
+        future::{FenceSignalFuture, GpuFuture, NowFuture},
+        semaphore::Semaphore,
+        AccessFlags, BufferMemoryBarrier, DependencyInfo, ImageMemoryBarrier, PipelineStages,
+        QueueFamilyOwnershipTransfer,
+    },
+    VulkanObject,
+};
+
+use super::error::GraphicsError;
+
+pub type UploadBuilder =
+    AutoCommandBufferBuilder<PrimaryAutoCommandBuffer, StandardCommandBufferAllocator>;
+
+type UploadFuture = FenceSignalFuture<CommandBufferExecFuture<NowFuture>>;
+
+/// A resource written by an upload. Uploaded resources are exclusive to one queue family, after
+/// each batch their ownership passes from the transfer queue family to the graphics one.
+pub enum UploadTarget {
+    Buffer(Arc<Buffer>),
+    /// The image and the layout the upload leaves it in.
+    Image(Arc<Image>, ImageLayout),
+}
+
+struct Batch {
+    builder: UploadBuilder,
+    targets: Vec<UploadTarget>,
+}
+
+/// Batches staging copies into a single command buffer on the transfer queue.
+pub(super) struct Uploader {
+    queue: Arc<Queue>,
+    /// The queue the uploaded resources are used on.
+    graphics_queue: Arc<Queue>,
+    batch: Mutex<Option<Batch>>,
+    /// Submitted batches that the next frame has to wait for.
+    submitted: Vec<Box<dyn GpuFuture>>,
+    /// The most recently submitted batch.
+    last_submitted: Option<Arc<UploadFuture>>,
+    /// Ownership transfers that may still be executing.
+    transfers: Vec<OwnershipTransfer>,
+}
+
+/// A flushed upload batch.
+#[derive(Clone)]
+pub struct UploadHandle {
+    /// `None` if nothing was ever uploaded.
+    future: Option<Arc<UploadFuture>>,
+}
+
+impl UploadHandle {
+    /// Blocks until the batch and every batch submitted before it have finished.
+    /// Frames already wait for pending uploads on the gpu, this is only needed when the
+    /// uploaded data is read back or used outside of `Graphics::draw_frame`.
+    pub fn wait(&self) -> Result<(), GraphicsError> {
+        if let Some(future) = &self.future {
+            future.wait(None)?;
+        }
+        Ok(())
+    }
+}
+
+impl Uploader {
+    pub fn new(queue: Arc<Queue>, graphics_queue: Arc<Queue>) -> Self {
+        Self {
+            queue: queue,
+            graphics_queue: graphics_queue,
+            batch: Mutex::new(None),
+            submitted: Vec::new(),
+            last_submitted: None,
+            transfers: Vec::new(),
+        }
+    }
+
+    /// Records commands that write `target` into the current batch, starting a new one if needed.
+    pub fn record(
+        &self,
+        allocator: &StandardCommandBufferAllocator,
+        target: UploadTarget,
+        record: impl FnOnce(&mut UploadBuilder) -> Result<(), GraphicsError>,
+    ) -> Result<(), GraphicsError> {
+        let mut batch = self
+            .batch
+            .lock()
+            .map_err(|_| GraphicsError::vulkan("upload batch mutex is poisoned"))?;
+
+        if batch.is_none() {
+            *batch = Some(Batch {
+                builder: AutoCommandBufferBuilder::primary(
+                    allocator,
+                    self.queue.queue_family_index(),
+                    CommandBufferUsage::OneTimeSubmit,
+                )
+                .map_err(GraphicsError::vulkan)?,
+                targets: Vec::new(),
+            });
+        }
+
+        let batch = batch.as_mut().unwrap();
+        record(&mut batch.builder)?;
+        batch.targets.push(target);
+        Ok(())
+    }
+
+    /// Submits the current batch, if there is one. Without one the handle refers to the last
+    /// submitted batch.
+    pub fn flush(
+        &mut self,
+        allocator: &StandardCommandBufferAllocator,
+    ) -> Result<UploadHandle, GraphicsError> {
+        self.transfers.retain(|transfer| !transfer.is_finished());
+
+        let batch = self
+            .batch
+            .get_mut()
+            .map_err(|_| GraphicsError::vulkan("upload batch mutex is poisoned"))?
+            .take();
+
+        if let Some(Batch { builder, targets }) = batch {
+            let future = Arc::new(
+                builder
+                    .build()
+                    .map_err(GraphicsError::vulkan)?
+                    .execute(self.queue.clone())
+                    .map_err(GraphicsError::vulkan)?
+                    .then_signal_fence_and_flush()?,
+            );
+            if self.queue.queue_family_index() != self.graphics_queue.queue_family_index() {
+                // submitted after the batch and before the semaphore below, so both the
+                // semaphore and the acquire wait for the release
+                self.transfers.push(OwnershipTransfer::submit(
+                    allocator,
+                    &self.queue,
+                    &self.graphics_queue,
+                    targets,
+                )?);
+            }
+            // the fence only lets the cpu wait, the graphics queue waits on a semaphore that is
+            // signaled after the batch on the transfer queue
+            let semaphore = future.clone().then_signal_semaphore_and_flush()?;
+            self.submitted.push(semaphore.boxed());
+            self.last_submitted = Some(future);
+        }
+
+        Ok(UploadHandle {
+            future: self.last_submitted.clone(),
+        })
+    }
+
+    /// Flushes the current batch and hands out every submitted batch, so that the caller can
+    /// join them into a future on the graphics queue.
+    pub fn take_pending(
+        &mut self,
+        allocator: &StandardCommandBufferAllocator,
+    ) -> Result<Vec<Box<dyn GpuFuture>>, GraphicsError> {
+        self.flush(allocator)?;
+        Ok(std::mem::take(&mut self.submitted))
+    }
+}
+
+/// The release and acquire barriers that pass a batch's resources from the transfer queue family
+/// to the graphics queue family. `AutoCommandBufferBuilder` can't record queue family ownership
+/// transfers, so both are recorded and submitted outside of vulkano's synchronization.
+struct OwnershipTransfer {
+    /// Signaled once the acquire has executed, the release has by then as well.
+    fence: Fence,
+    _semaphore: Semaphore,
+    _command_buffers: [(UnsafeCommandBuffer, StandardCommandBufferAlloc); 2],
+    _targets: Vec<UploadTarget>,
+}
+
+impl OwnershipTransfer {
+    /// Releases `targets` on `transfer_queue` and acquires them on `graphics_queue`. The acquire
+    /// is submitted right away, so every later submission on `graphics_queue` sees the resources.
+    fn submit(
+        allocator: &StandardCommandBufferAllocator,
+        transfer_queue: &Arc<Queue>,
+        graphics_queue: &Arc<Queue>,
+        targets: Vec<UploadTarget>,
+    ) -> Result<Self, GraphicsError> {
+        let ownership = QueueFamilyOwnershipTransfer::ExclusiveBetweenLocal {
+            src_index: transfer_queue.queue_family_index(),
+            dst_index: graphics_queue.queue_family_index(),
+        };
+        let no_access = (PipelineStages::empty(), AccessFlags::empty());
+        let release = barriers(
+            &targets,
+            ownership,
+            (PipelineStages::ALL_TRANSFER, AccessFlags::TRANSFER_WRITE),
+            no_access,
+        );
+        let acquire = barriers(
+            &targets,
+            ownership,
+            no_access,
+            (PipelineStages::ALL_COMMANDS, AccessFlags::MEMORY_READ),
+        );
+
+        let release = record_barriers(allocator, transfer_queue.queue_family_index(), &release)?;
+        let acquire = record_barriers(allocator, graphics_queue.queue_family_index(), &acquire)?;
+
+        let device = transfer_queue.device();
+        let semaphore =
+            Semaphore::new(device.clone(), Default::default()).map_err(GraphicsError::vulkan)?;
+        let fence =
+            Fence::new(device.clone(), Default::default()).map_err(GraphicsError::vulkan)?;
+
+        unsafe {
+            submit_raw(transfer_queue, &release.0, None, Some(&semaphore), None)?;
+            submit_raw(
+                graphics_queue,
+                &acquire.0,
+                Some(&semaphore),
+                None,
+                Some(&fence),
+            )?;
+        }
+
+        Ok(Self {
+            fence: fence,
+            _semaphore: semaphore,
+            _command_buffers: [release, acquire],
+            _targets: targets,
+        })
+    }
+
+    fn is_finished(&self) -> bool {
+        // vulkano doesn't know about the raw submission, so `is_signaled` can't be used
+        self.fence.wait(Some(Duration::ZERO)).is_ok()
+    }
+}
+
+impl Drop for OwnershipTransfer {
+    fn drop(&mut self) {
+        // the command buffers and the semaphore must not be destroyed while in use
+        if let Err(e) = self.fence.wait(None) {
+            log::error!("Waiting for an ownership transfer failed! {e}");
+        }
+    }
+}
+
+type Scope = (PipelineStages, AccessFlags);
+
+/// Ownership transfer barriers over the whole of each target.
+fn barriers(
+    targets: &[UploadTarget],
+    ownership: QueueFamilyOwnershipTransfer,
+    (src_stages, src_access): Scope,
+    (dst_stages, dst_access): Scope,
+) -> DependencyInfo {
+    let mut dependency_info = DependencyInfo::default();
+    for target in targets {
+        match target {
+            UploadTarget::Buffer(buffer) => {
+                dependency_info
+                    .buffer_memory_barriers
+                    .push(BufferMemoryBarrier {
+                        src_stages: src_stages,
+                        src_access: src_access,
+                        dst_stages: dst_stages,
+                        dst_access: dst_access,
+                        queue_family_ownership_transfer: Some(ownership),
+                        range: 0..buffer.size(),
+                        ..BufferMemoryBarrier::buffer(buffer.clone())
+                    })
+            }
+            UploadTarget::Image(image, layout) => {
+                dependency_info
+                    .image_memory_barriers
+                    .push(ImageMemoryBarrier {
+                        src_stages: src_stages,
+                        src_access: src_access,
+                        dst_stages: dst_stages,
+                        dst_access: dst_access,
+                        old_layout: *layout,
+                        new_layout: *layout,
+                        queue_family_ownership_transfer: Some(ownership),
+                        subresource_range: image.subresource_range(),
+                        ..ImageMemoryBarrier::image(image.clone())
+                    })
+            }
+        }
+    }
+    dependency_info
+}
+
+fn record_barriers(
+    allocator: &StandardCommandBufferAllocator,
+    queue_family_index: u32,
+    dependency_info: &DependencyInfo,
+) -> Result<(UnsafeCommandBuffer, StandardCommandBufferAlloc), GraphicsError> {
+    let builder_alloc = allocator
+        .allocate(queue_family_index, CommandBufferLevel::Primary, 1)?
+        .next()
+        .ok_or(GraphicsError::OutOfMemory)?;
+
+    // the allocation is kept next to the command buffer until the transfer has finished
+    let command_buffer = unsafe {
+        let mut builder = UnsafeCommandBufferBuilder::new(
+            builder_alloc.inner(),
+            CommandBufferBeginInfo {
+                usage: CommandBufferUsage::OneTimeSubmit,
+                ..Default::default()
+            },
+        )?;
+        builder.pipeline_barrier(dependency_info);
+        builder.build()?
+    };
+
+    Ok((command_buffer, builder_alloc.into_alloc()))
+}
+
+/// Submits `command_buffer` to `queue` while holding the queue's lock.
+///
+/// # Safety
+///
+/// Everything passed in has to stay alive until the submission has executed.
+unsafe fn submit_raw(
+    queue: &Arc<Queue>,
+    command_buffer: &UnsafeCommandBuffer,
+    wait: Option<&Semaphore>,
+    signal: Option<&Semaphore>,
+    fence: Option<&Fence>,
+) -> Result<(), GraphicsError> {
+    let wait_semaphores: Vec<_> = wait.iter().map(|p| p.handle()).collect();
+    let wait_stages = vec![ash::vk::PipelineStageFlags::ALL_COMMANDS; wait_semaphores.len()];
+    let signal_semaphores: Vec<_> = signal.iter().map(|p| p.handle()).collect();
+    let command_buffers = [command_buffer.handle()];
+
+    let submit_info = ash::vk::SubmitInfo::builder()
+        .wait_semaphores(&wait_semaphores)
+        .wait_dst_stage_mask(&wait_stages)
+        .command_buffers(&command_buffers)
+        .signal_semaphores(&signal_semaphores);
+
+    let result = queue.with(|_guard| {
+        let fns = queue.device().fns();
+        (fns.v1_0.queue_submit)(
+            queue.handle(),
+            1,
+            &*submit_info,
+            fence.map_or(ash::vk::Fence::null(), |p| p.handle()),
+        )
+    });
+
+    match result {
+        ash::vk::Result::SUCCESS => Ok(()),
+        ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY | ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => {
+            Err(GraphicsError::OutOfMemory)
+        }
+        ash::vk::Result::ERROR_DEVICE_LOST => Err(GraphicsError::DeviceLost),
+        e => Err(GraphicsError::vulkan(e)),
+    }
+}
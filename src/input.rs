@@ -10,7 +10,7 @@ use winit::{event::Event, window::Window};
 mod keyboard;
 pub use keyboard::Keyboard;
 
-mod mouse;
+pub mod mouse;
 pub use mouse::Mouse;
 
 #[derive(Clone, Debug)]
@@ -25,6 +25,14 @@ impl Keyboard {
         }
     }
 
+    /// Pressed this frame or held.
+    pub fn is_key_down(&self, keycode: u32) -> bool {
+        match self.get_key_state(keycode) {
+            Some(ButtonState::Pressed(_)) | Some(ButtonState::Held(_)) => true,
+            _ => false,
+        }
+    }
+
     pub fn get_key_state(&self, keycode: u32) -> Option<ButtonState> {
         self.key_map.read().ok()?.get(&keycode).cloned()
     }
@@ -6,15 +6,26 @@ use std::{
 
 use cgmath::Vector2;
 use winit::{
-    event::{DeviceEvent, ElementState, Event, WindowEvent},
+    event::{DeviceEvent, ElementState, Event, MouseScrollDelta, WindowEvent},
     window::Window,
 };
 
 use super::{ButtonState, BypassHasher};
 
+// device event button ids as reported on linux, other platforms may number them differently
+pub const MOUSE_LEFT: u32 = 1;
+pub const MOUSE_MIDDLE: u32 = 2;
+pub const MOUSE_RIGHT: u32 = 3;
+
+/// Touchpads scroll in pixels, wheels in lines.
+const PIXELS_PER_SCROLL_LINE: f64 = 20.0;
+
 pub struct Mouse {
     pub cursor_position: Cell<Vector2<f64>>,
+    /// Raw movement since the last `clear_presses`.
     pub mouse_movement: Cell<Vector2<f64>>,
+    /// Lines scrolled since the last `clear_presses`, positive is away from the user.
+    pub scroll_delta: Cell<f64>,
     button_map: RwLock<HashMap<u32, ButtonState, BypassHasher>>,
 }
 
@@ -24,6 +35,7 @@ impl Mouse {
             Self {
                 cursor_position: Cell::new(Vector2 { x: 0.0, y: 0.0 }),
                 mouse_movement: Cell::new(Vector2 { x: 0.0, y: 0.0 }),
+                scroll_delta: Cell::new(0.0),
                 button_map: RwLock::new(HashMap::with_hasher(super::BypassHasher {})),
             },
             Mouse::_event_handler,
@@ -44,6 +56,14 @@ impl Mouse {
         }
     }
 
+    /// Pressed this frame or held.
+    pub fn is_button_down(&self, button_id: u32) -> bool {
+        match self.get_button_state(button_id) {
+            Some(ButtonState::Pressed(_)) | Some(ButtonState::Held(_)) => true,
+            _ => false,
+        }
+    }
+
     pub fn get_button_state(&self, button_id: u32) -> Option<ButtonState> {
         self.button_map.read().ok()?.get(&button_id).cloned()
     }
@@ -98,6 +118,16 @@ impl Mouse {
                     });
                     return true;
                 }
+                if let WindowEvent::MouseWheel { delta, .. } = event {
+                    let lines = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => *y as f64,
+                        MouseScrollDelta::PixelDelta(position) => {
+                            position.y / PIXELS_PER_SCROLL_LINE
+                        }
+                    };
+                    self.scroll_delta.set(self.scroll_delta.get() + lines);
+                    return true;
+                }
 
                 return false;
             }
@@ -105,7 +135,11 @@ impl Mouse {
         }
     }
 
+    /// Also resets the accumulated movement and scrolling.
     pub fn clear_presses(&self) {
+        self.mouse_movement.set(Vector2 { x: 0.0, y: 0.0 });
+        self.scroll_delta.set(0.0);
+
         match self.button_map.write() {
             Ok(mut guard) => {
                 guard.iter_mut().for_each(|(_, state)| {
@@ -3,7 +3,8 @@
 use std::sync::Arc;
 
 use app::App;
-use graphics::Graphics;
+use graphics::{config::GraphicsConfig, error::GraphicsError, Graphics};
+use vulkano::format::Format;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::ControlFlow,
@@ -12,16 +13,42 @@ use winit::{
 
 #[path = "app.rs"]
 mod app;
+mod camera_controller;
+mod golden;
 mod graphics;
 mod input;
 
 fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|p| p == "--golden") {
+        let bless = args.iter().any(|p| p == "--bless");
+        std::process::exit(match golden::run(bless) {
+            golden::GoldenOutcome::Passed => 0,
+            golden::GoldenOutcome::Failed => 1,
+            golden::GoldenOutcome::Skipped => golden::SKIPPED_EXIT_CODE,
+        });
+    }
+
     // initialize subsystems
-    let (mut gfx, event_loop) = Graphics::new();
+    let config = GraphicsConfig::load_or_default("graphics.ron");
+
+    if args.iter().any(|p| p == "--device-report") {
+        print_device_report(config);
+        return;
+    }
+    let (mut gfx, event_loop) = match Graphics::new(config) {
+        Ok(created) => created,
+        Err(e) => exit_with_error(e),
+    };
     let input = input::Input::new(gfx.get_window());
 
     // initialize app and pass it a reference to each subsystem
-    let app = App::new(&mut gfx, input.clone());
+    let mut app = match App::new(&mut gfx, input.clone()) {
+        Ok(app) => app,
+        Err(e) => exit_with_error(e),
+    };
 
     let mut minimized = false;
 
@@ -46,7 +73,10 @@ fn main() {
                 minimized = is_minimized(gfx.get_window());
 
                 if !minimized {
-                    app.resize_callback(&mut gfx);
+                    if let Err(e) = app.resize_callback(&mut gfx) {
+                        log::error!("Failed to recreate the swapchain: {e}");
+                        *control_flow = ControlFlow::Exit;
+                    }
                 }
             },
             Event::WindowEvent {
@@ -58,7 +88,15 @@ fn main() {
             Event::RedrawEventsCleared => {
                 app.run(&gfx);
                 if !minimized {
-                    gfx.draw_frame()
+                    if let Err(e) = gfx.draw_frame() {
+                        log::error!("Failed to draw frame: {e}");
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+
+                    if app.screenshot_requested() {
+                        save_screenshot(&mut gfx);
+                    }
                 }
                 input.clear_presses();
             }
@@ -67,6 +105,46 @@ fn main() {
     });
 }
 
+fn save_screenshot(gfx: &mut Graphics) {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|p| p.as_secs())
+        .unwrap_or(0);
+    let path = format!("screenshots/{timestamp}.png");
+
+    let result = std::fs::create_dir_all("screenshots")
+        .map_err(|e| e.to_string())
+        .and_then(|_| gfx.capture_frame().map_err(|e| e.to_string()))
+        .and_then(|frame| frame.save_png(&path).map_err(|e| e.to_string()));
+
+    match result {
+        Ok(_) => log::info!("Saved screenshot to {path}"),
+        Err(e) => log::error!("Failed to save screenshot: {e}"),
+    }
+}
+
+/// Prints the selected device and every other device, without opening a window.
+fn print_device_report(config: GraphicsConfig) {
+    let gfx = match Graphics::new_headless(config, [1, 1], Format::R8G8B8A8_UNORM) {
+        Ok(gfx) => gfx,
+        Err(e) => exit_with_error(e),
+    };
+
+    println!("{}", gfx.device_report());
+    println!("available devices:");
+    match gfx.available_devices() {
+        Ok(devices) => devices.iter().for_each(|p| print!("{p}")),
+        Err(e) => println!("could not enumerate devices: {e}"),
+    }
+}
+
+/// Reports an error that happened during startup and exits.
+fn exit_with_error(error: GraphicsError) -> ! {
+    log::error!("{error}");
+    eprintln!("Failed to start: {error}");
+    std::process::exit(1)
+}
+
 fn is_minimized(window: Arc<Window>) -> bool {
     let extent = window.inner_size();
 
@@ -0,0 +1,24 @@
+use std::process::Command;
+
+/// Matches `SKIPPED_EXIT_CODE` in `src/golden.rs`.
+const SKIPPED_EXIT_CODE: i32 = 77;
+
+/// Runs the golden image comparison of the binary, see `src/golden.rs`.
+#[test]
+fn drawables_match_golden_images() {
+    let status = Command::new(env!("CARGO_BIN_EXE_batako"))
+        .arg("--golden")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .status()
+        .expect("Failed to launch batako.");
+
+    if status.code() == Some(SKIPPED_EXIT_CODE) {
+        eprintln!("SKIPPED: golden image comparison needs a Vulkan library, nothing was compared.");
+        return;
+    }
+
+    assert!(
+        status.success(),
+        "Golden image comparison failed, the rendered frames and diffs are in target/golden/."
+    );
+}